@@ -0,0 +1,165 @@
+//! Times the work done on every frame of the live camera preview.
+//!
+//! The app is a binary, so the modules it needs are pulled in by path. Run
+//! with `cargo bench --bench preview`.
+
+// `preview_renderer` finds `border_radius` as a sibling, like in the app
+#[path = "../src/utils/border_radius.rs"]
+mod border_radius;
+#[path = "../src/utils/chroma_key.rs"]
+mod chroma_key;
+#[path = "../src/utils/color_lut.rs"]
+mod color_lut;
+#[path = "../src/utils/crop.rs"]
+mod crop;
+#[path = "../src/utils/preview_renderer.rs"]
+mod preview_renderer;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use border_radius::{BorderRadius, CornerMask};
+use chroma_key::ChromaKeyer;
+use color_lut::Lut3d;
+use image::{Rgba, RgbaImage};
+use preview_renderer::PreviewRenderer;
+
+const RUN_TIME: Duration = Duration::from_secs(2);
+
+fn bench(name: &str, mut f: impl FnMut()) {
+    // warm up caches and the allocator
+    for _ in 0..3 {
+        f();
+    }
+    let started = Instant::now();
+    let mut iterations = 0u32;
+    while started.elapsed() < RUN_TIME {
+        f();
+        iterations += 1;
+    }
+    let per_iteration = started.elapsed() / iterations;
+    println!(
+        "{:<40} {:>10.3} ms/frame ({} frames)",
+        name,
+        per_iteration.as_secs_f64() * 1000.0,
+        iterations
+    );
+}
+
+/// A `.cube` file with `size` points along each axis.
+fn cube_file(size: usize) -> String {
+    let mut cube = format!("LUT_3D_SIZE {}\n", size);
+    let step = 1.0 / (size - 1) as f32;
+    for b in 0..size {
+        for g in 0..size {
+            for r in 0..size {
+                cube += &format!(
+                    "{:.6} {:.6} {:.6}\n",
+                    r as f32 * step,
+                    g as f32 * step,
+                    b as f32 * step
+                );
+            }
+        }
+    }
+    cube
+}
+
+/// A gradient, so the frame isn't trivially compressible or uniform.
+fn camera_frame(width: u32, height: u32) -> RgbaImage {
+    RgbaImage::from_fn(width, height, |x, y| {
+        Rgba([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8, 255])
+    })
+}
+
+fn main() {
+    let radius = BorderRadius::from(48);
+    for (width, height) in [(1280, 720), (1920, 1080), (3840, 2160)] {
+        let frame = camera_frame(width, height);
+
+        // what every preview frame used to go through, decoding into a new
+        // image each time
+        bench(&format!("{}x{} full size", width, height), || {
+            let decoded = frame.clone();
+            let left = (width - height) / 2;
+            let mut preview =
+                image::imageops::crop_imm(&decoded, left, 0, height, height).to_image();
+            image::imageops::flip_horizontal_in_place(&mut preview);
+            border_radius::round(&mut preview, &radius);
+            black_box(preview);
+        });
+
+        for (display_width, display_height) in [(1280, 800), (1920, 1080)] {
+            let mut renderer = PreviewRenderer::new(radius, true, Some(1.0));
+            renderer.set_max_size(display_width, display_height);
+            bench(
+                &format!(
+                    "{}x{} on a {}x{} display",
+                    width, height, display_width, display_height
+                ),
+                || {
+                    black_box(renderer.render(&frame, (0.5, 0.5)));
+                },
+            );
+        }
+
+        let mut renderer = PreviewRenderer::new(radius, true, Some(1.0));
+        renderer.set_max_size(1280, 800);
+        renderer.set_lut(Some(
+            Lut3d::from_fn(17, |[r, g, b]| {
+                let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+                [luma, luma, luma]
+            })
+            .into(),
+        ));
+        bench(
+            &format!("{}x{} on a 1280x800 display, filtered", width, height),
+            || {
+                black_box(renderer.render(&frame, (0.5, 0.5)));
+            },
+        );
+
+        let mut renderer = PreviewRenderer::new(radius, true, Some(1.0));
+        renderer.set_max_size(1280, 800);
+        renderer.set_chroma_key(Some((
+            ChromaKeyer::new([0, 177, 64], 0.4, 0.2, 0.5),
+            camera_frame(1920, 1080).into(),
+        )));
+        bench(
+            &format!("{}x{} on a 1280x800 display, keyed", width, height),
+            || {
+                black_box(renderer.render(&frame, (0.5, 0.5)));
+            },
+        );
+
+        let mut renderer = PreviewRenderer::new(radius, true, Some(1.0));
+        renderer.set_max_size(1280, 800);
+        renderer.set_zoom(2.0, (0.4, 0.6));
+        bench(
+            &format!("{}x{} on a 1280x800 display, zoomed", width, height),
+            || {
+                black_box(renderer.render(&frame, (0.5, 0.5)));
+            },
+        );
+
+        // a template with a square frame followed by a tall one
+        let mut renderer = PreviewRenderer::new(radius, true, Some(1.0));
+        renderer.set_max_size(1280, 800);
+        renderer.set_aspect_ratio(Some(2.0 / 3.0));
+        bench(
+            &format!("{}x{} on a 1280x800 display, 2:3 frame", width, height),
+            || {
+                black_box(renderer.render(&frame, (0.5, 0.5)));
+            },
+        );
+    }
+
+    bench("corner mask", || {
+        black_box(CornerMask::new(radius));
+    });
+
+    let cube = cube_file(33);
+    bench("parsing a 33 point .cube", || {
+        black_box(Lut3d::parse_cube(&cube).expect("the LUT is valid"));
+    });
+}
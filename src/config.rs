@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde::{
     de::{Error, Expected},
     Deserialize, Serialize,
@@ -51,6 +53,87 @@ pub(crate) struct Config {
     pub mirror_preview: bool,
     #[serde(rename = "mirrorOutput")]
     pub mirror_output: bool,
+    /// How far the camera's frames are turned clockwise, for cameras mounted
+    /// on their side.
+    #[serde(rename = "cameraRotation", default)]
+    pub camera_rotation: CameraRotation,
+    #[serde(rename = "deliveryOptions", default = "default_delivery_options")]
+    pub delivery_options: Vec<DeliveryOption>,
+    #[serde(rename = "printCommand", default)]
+    pub print_command: Vec<String>,
+    #[serde(rename = "qrServerEndpoint", default)]
+    pub qr_server_endpoint: String,
+    #[serde(rename = "printSheet", default)]
+    pub print_sheet: Option<PrintSheet>,
+    /// How the strip is encoded when it's emailed, uploaded or printed.
+    #[serde(rename = "outputFormat", default)]
+    pub output_format: OutputFormat,
+    /// Looks guests can pick from before taking their photos. The first one is
+    /// picked to begin with.
+    #[serde(default)]
+    pub looks: Vec<Look>,
+    /// Replaces a coloured backdrop behind the guests when set.
+    #[serde(rename = "chromaKey", default)]
+    pub chroma_key: Option<ChromaKey>,
+    /// Camera controls locked by the operator, by camera name. They're
+    /// applied whenever the camera is opened.
+    #[serde(rename = "cameraControls", default)]
+    pub camera_controls: HashMap<String, Vec<CameraControlSetting>>,
+    /// Where camera controls locked from the camera settings are saved.
+    /// They're read when the booth starts and replace the ones in
+    /// `cameraControls` for the same camera.
+    #[serde(
+        rename = "cameraControlsFile",
+        default = "default_camera_controls_file"
+    )]
+    pub camera_controls_file: String,
+    /// Zooms into part of what the camera sees, for the preview and the
+    /// photos.
+    #[serde(rename = "digitalZoom", default)]
+    pub digital_zoom: DigitalZoom,
+    /// Where the digital zoom set in admin mode is saved. It's read when the
+    /// booth starts and replaces `digitalZoom`.
+    #[serde(rename = "digitalZoomFile", default = "default_digital_zoom_file")]
+    pub digital_zoom_file: String,
+    /// Evens out the exposure and colour of the photos before they're
+    /// rendered when set.
+    #[serde(default)]
+    pub normalize: Option<Normalize>,
+    /// Shows over the live preview which parts of the photo the frame's
+    /// shape and the template's overlay and texts will hide, when set.
+    #[serde(rename = "templateGuide", default)]
+    pub template_guide: Option<TemplateGuide>,
+    /// Which part of each photo is kept when it's cropped to fit a frame.
+    #[serde(rename = "cropStrategy", default)]
+    pub crop_strategy: CropStrategy,
+    /// The filter used to scale photos and template images when rendering.
+    #[serde(rename = "resampleFilter", default)]
+    pub resample_filter: ResampleFilter,
+    /// How many frames are taken for every photo. The sharpest one is kept.
+    #[serde(rename = "burstCount", default = "default_burst_count")]
+    pub burst_count: u32,
+    /// Milliseconds between the counter running out and the instant the
+    /// photo is taken from. Negative values pick an earlier frame.
+    #[serde(rename = "shutterOffset", default)]
+    pub shutter_offset: i64,
+    /// Sends the individual photos along with the strip when set.
+    #[serde(rename = "frameAttachments", default)]
+    pub frame_attachments: Option<FrameAttachments>,
+    /// Sends a looping animation of the photos along with the strip when set.
+    #[serde(rename = "animatedLoop", default)]
+    pub animated_loop: Option<AnimatedLoop>,
+    /// Written into the artist/author metadata of the output.
+    #[serde(default)]
+    pub artist: Option<String>,
+    /// Written into the copyright metadata of the output.
+    #[serde(default)]
+    pub copyright: Option<String>,
+    /// strftime-style format used for `{date}` in template texts.
+    #[serde(rename = "dateFormat", default = "default_date_format")]
+    pub date_format: String,
+    /// strftime-style format used for `{time}` in template texts.
+    #[serde(rename = "timeFormat", default = "default_time_format")]
+    pub time_format: String,
 }
 
 impl Config {
@@ -61,17 +144,592 @@ impl Config {
                 0,
                 &ExpectedLength::new(config.template.frames.len(), 1, "template.frames"),
             ))
+        } else if config.enabled_delivery_options().count() < 1 {
+            Err(serde_json::Error::invalid_length(
+                0,
+                &ExpectedLength::new(
+                    config.enabled_delivery_options().count(),
+                    1,
+                    "deliveryOptions",
+                ),
+            ))
+        } else if config
+            .enabled_delivery_options()
+            .any(|option| option.methods.is_empty())
+        {
+            Err(serde_json::Error::custom(
+                "deliveryOptions.methods needs at least one method",
+            ))
+        } else if config.enabled_delivery_options().any(|option| {
+            option.methods.contains(&DeliveryMethod::Discard) && option.methods.len() > 1
+        }) {
+            Err(serde_json::Error::custom(
+                "deliveryOptions.methods can't combine discard with other methods",
+            ))
+        } else if config.uses_delivery_method(DeliveryMethod::Print)
+            && config.print_command.is_empty()
+        {
+            Err(serde_json::Error::custom(
+                "printCommand can't be empty when printing is a delivery method",
+            ))
+        } else if config.uses_delivery_method(DeliveryMethod::Qr)
+            && config.qr_server_endpoint.trim().is_empty()
+        {
+            Err(serde_json::Error::custom(
+                "qrServerEndpoint can't be empty when QR codes are a delivery method",
+            ))
         } else {
             Ok(config)
         }
     }
+
+    /// Whether any enabled delivery option uses `method`.
+    fn uses_delivery_method(&self, method: DeliveryMethod) -> bool {
+        self.enabled_delivery_options()
+            .any(|option| option.methods.contains(&method))
+    }
+
+    /// The delivery options the guest is allowed to pick from, in the order
+    /// they should be shown.
+    pub fn enabled_delivery_options(&self) -> impl Iterator<Item = &DeliveryOption> {
+        self.delivery_options.iter().filter(|option| option.enabled)
+    }
+}
+
+fn default_delivery_options() -> Vec<DeliveryOption> {
+    vec![DeliveryOption {
+        label: "Email".to_string(),
+        methods: vec![DeliveryMethod::Email],
+        enabled: true,
+        print_copies: 1,
+    }]
+}
+
+fn default_date_format() -> String {
+    "%B %-d, %Y".to_string()
+}
+
+fn default_time_format() -> String {
+    "%-I:%M %p".to_string()
+}
+
+fn default_camera_controls_file() -> String {
+    "camera_controls.json".to_string()
+}
+
+fn default_digital_zoom_file() -> String {
+    "digital_zoom.json".to_string()
+}
+
+fn default_burst_count() -> u32 {
+    1
+}
+
+fn default_output_quality() -> u8 {
+    90
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_print_copies() -> u32 {
+    1
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct FrameAttachments {
+    /// Crop each photo to the shape of its frame in the template instead of
+    /// sending everything the camera saw.
+    #[serde(default)]
+    pub crop: bool,
+    /// The longest side of each photo in pixels. Bigger photos are scaled
+    /// down to keep emails small.
+    #[serde(rename = "maxSize", default = "default_frame_attachment_max_size")]
+    pub max_size: u32,
+}
+
+fn default_frame_attachment_max_size() -> u32 {
+    1920
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct AnimatedLoop {
+    #[serde(default)]
+    pub format: AnimatedLoopFormat,
+    /// How long each photo is shown, in milliseconds.
+    #[serde(rename = "frameDelay", default = "default_animated_loop_frame_delay")]
+    pub frame_delay: u32,
+    /// Play the photos forwards and then backwards instead of jumping back to
+    /// the first one.
+    #[serde(default)]
+    pub boomerang: bool,
+    /// The longest side of the animation in pixels.
+    #[serde(rename = "maxSize", default = "default_animated_loop_max_size")]
+    pub max_size: u32,
+}
+
+fn default_animated_loop_frame_delay() -> u32 {
+    500
+}
+
+fn default_animated_loop_max_size() -> u32 {
+    640
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum AnimatedLoopFormat {
+    #[default]
+    #[serde(rename = "gif")]
+    Gif,
+    /// Lossless WebP, which looks better than GIF but is bigger.
+    #[serde(rename = "webp")]
+    WebP,
+}
+
+impl AnimatedLoopFormat {
+    pub fn mime(&self) -> &'static str {
+        match self {
+            AnimatedLoopFormat::Gif => "image/gif",
+            AnimatedLoopFormat::WebP => "image/webp",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AnimatedLoopFormat::Gif => "gif",
+            AnimatedLoopFormat::WebP => "webp",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct CameraControlSetting {
+    /// `brightness`, `exposure`, `whiteBalance`, `focus`, `zoom` and so on,
+    /// or the number of a control that only some cameras have.
+    pub control: String,
+    pub value: CameraControlValue,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(untagged)]
+pub(crate) enum CameraControlValue {
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub(crate) struct DigitalZoom {
+    /// How far to zoom in, where 1 shows everything the camera sees.
+    #[serde(default = "default_zoom")]
+    pub zoom: f32,
+    /// The centre of the part zoomed into, as fractions of the camera's width
+    /// and height.
+    #[serde(default = "default_zoom_centre")]
+    pub x: f32,
+    #[serde(default = "default_zoom_centre")]
+    pub y: f32,
+}
+
+impl Default for DigitalZoom {
+    fn default() -> Self {
+        DigitalZoom {
+            zoom: default_zoom(),
+            x: default_zoom_centre(),
+            y: default_zoom_centre(),
+        }
+    }
+}
+
+fn default_zoom() -> f32 {
+    1.0
+}
+
+fn default_zoom_centre() -> f32 {
+    0.5
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct TemplateGuide {
+    /// How strongly the guide shows over the preview, from 0 to 1.
+    #[serde(default = "default_template_guide_opacity")]
+    pub opacity: f32,
+}
+
+fn default_template_guide_opacity() -> f32 {
+    0.5
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct Normalize {
+    /// Stretch the brightness of the photos to use the full range.
+    #[serde(rename = "autoLevels", default = "default_true")]
+    pub auto_levels: bool,
+    /// Balance the colours of the photos so they average out to grey.
+    #[serde(rename = "whiteBalance", default = "default_true")]
+    pub white_balance: bool,
+    /// The fraction of the darkest and of the brightest pixels that can be
+    /// clipped when stretching the brightness.
+    #[serde(default = "default_normalize_clip")]
+    pub clip: f32,
+    #[serde(default)]
+    pub mode: NormalizeMode,
+}
+
+fn default_normalize_clip() -> f32 {
+    0.005
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum NormalizeMode {
+    /// Correct every photo on its own.
+    #[default]
+    #[serde(rename = "independent")]
+    Independent,
+    /// Match the brightness and tint of the photos to each other, then
+    /// correct them all the same way, so the strip looks consistent.
+    #[serde(rename = "matched")]
+    Matched,
+}
+
+/// A look guests can pick on the camera screen.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct Look {
+    pub label: String,
+    /// The colour filter, or none to keep the photos as they are.
+    #[serde(default)]
+    pub filter: Option<ColorFilter>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub(crate) enum ColorFilter {
+    #[serde(rename = "blackAndWhite")]
+    BlackAndWhite,
+    #[serde(rename = "sepia")]
+    Sepia,
+    #[serde(rename = "highContrast")]
+    HighContrast,
+    #[serde(rename = "warm")]
+    Warm,
+    #[serde(rename = "cool")]
+    Cool,
+    /// A 3D LUT in the `.cube` format.
+    #[serde(rename = "lut")]
+    Lut { path: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct ChromaKey {
+    /// The colour of the backdrop as RGB.
+    #[serde(rename = "keyColor", default = "default_key_color")]
+    pub key_color: [u8; 3],
+    /// How far from the key colour a colour can be and still count as
+    /// backdrop, from 0 for only the key colour to 1 for grey.
+    #[serde(default = "default_chroma_key_tolerance")]
+    pub tolerance: f32,
+    /// How much further away colours are blended with the background, which
+    /// softens the edges around the guests.
+    #[serde(default = "default_chroma_key_softness")]
+    pub softness: f32,
+    /// How much of the key colour reflected onto the guests is taken out,
+    /// from 0 to 1.
+    #[serde(rename = "spillSuppression", default = "default_spill_suppression")]
+    pub spill_suppression: f32,
+    /// Backgrounds guests can pick from. The first one is picked to begin
+    /// with.
+    pub backgrounds: Vec<Background>,
+}
+
+fn default_key_color() -> [u8; 3] {
+    [0, 177, 64]
+}
+
+fn default_chroma_key_tolerance() -> f32 {
+    0.4
+}
+
+fn default_chroma_key_softness() -> f32 {
+    0.2
+}
+
+fn default_spill_suppression() -> f32 {
+    0.5
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct Background {
+    pub label: String,
+    pub path: String,
+}
+
+/// How the part of a photo that's kept when cropping it is chosen. The same
+/// strategy is used for the preview and the strip.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(tag = "type")]
+pub(crate) enum CropStrategy {
+    /// Trim the same amount off both sides.
+    #[default]
+    #[serde(rename = "centre")]
+    Centre,
+    /// Keep the crop centred on a point, given as fractions of the photo's
+    /// width and height from the top left of the camera's own view, as far
+    /// as the photo allows.
+    #[serde(rename = "focalPoint")]
+    FocalPoint { x: f32, y: f32 },
+    /// Centre the crop on the box around the guests' faces, finding them with
+    /// the SeetaFace frontal face model at `model`. Photos without faces are
+    /// centred.
+    #[serde(rename = "faces")]
+    Faces { model: String },
+}
+
+/// A clockwise rotation in degrees: 0, 90, 180 or 270.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(try_from = "u16", into = "u16")]
+pub(crate) enum CameraRotation {
+    #[default]
+    None,
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
+}
+
+impl TryFrom<u16> for CameraRotation {
+    type Error = String;
+
+    fn try_from(degrees: u16) -> std::result::Result<Self, Self::Error> {
+        match degrees {
+            0 => Ok(CameraRotation::None),
+            90 => Ok(CameraRotation::Clockwise90),
+            180 => Ok(CameraRotation::Clockwise180),
+            270 => Ok(CameraRotation::Clockwise270),
+            degrees => Err(format!(
+                "{} isn't a camera rotation, expected 0, 90, 180 or 270",
+                degrees
+            )),
+        }
+    }
+}
+
+impl From<CameraRotation> for u16 {
+    fn from(rotation: CameraRotation) -> u16 {
+        match rotation {
+            CameraRotation::None => 0,
+            CameraRotation::Clockwise90 => 90,
+            CameraRotation::Clockwise180 => 180,
+            CameraRotation::Clockwise270 => 270,
+        }
+    }
+}
+
+/// How images are scaled when the strip is rendered, from fastest to best
+/// looking.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum ResampleFilter {
+    #[serde(rename = "nearest")]
+    Nearest,
+    #[serde(rename = "triangle")]
+    Triangle,
+    #[serde(rename = "catmullRom")]
+    CatmullRom,
+    #[serde(rename = "gaussian")]
+    Gaussian,
+    #[default]
+    #[serde(rename = "lanczos3")]
+    Lanczos3,
+}
+
+impl ResampleFilter {
+    pub fn filter_type(&self) -> image::imageops::FilterType {
+        match self {
+            ResampleFilter::Nearest => image::imageops::FilterType::Nearest,
+            ResampleFilter::Triangle => image::imageops::FilterType::Triangle,
+            ResampleFilter::CatmullRom => image::imageops::FilterType::CatmullRom,
+            ResampleFilter::Gaussian => image::imageops::FilterType::Gaussian,
+            ResampleFilter::Lanczos3 => image::imageops::FilterType::Lanczos3,
+        }
+    }
+}
+
+/// The file format the strip is encoded in when it leaves the booth.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(tag = "type")]
+pub(crate) enum OutputFormat {
+    #[default]
+    #[serde(rename = "png")]
+    Png,
+    /// JPEG with a quality from 1 to 100. Transparent areas become white.
+    #[serde(rename = "jpeg")]
+    Jpeg {
+        #[serde(default = "default_output_quality")]
+        quality: u8,
+    },
+    /// Lossless WebP.
+    #[serde(rename = "webp")]
+    WebP,
+    /// A PDF with one page the physical size of the strip, holding the strip
+    /// as a JPEG of the given quality.
+    #[serde(rename = "pdf")]
+    Pdf {
+        #[serde(default = "default_output_quality")]
+        quality: u8,
+    },
+}
+
+impl OutputFormat {
+    pub fn mime(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => "image/png",
+            OutputFormat::Jpeg { .. } => "image/jpeg",
+            OutputFormat::WebP => "image/webp",
+            OutputFormat::Pdf { .. } => "application/pdf",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg { .. } => "jpg",
+            OutputFormat::WebP => "webp",
+            OutputFormat::Pdf { .. } => "pdf",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct DeliveryOption {
+    pub label: String,
+    /// Methods run one after the other, e.g. `["print", "email"]`.
+    pub methods: Vec<DeliveryMethod>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(rename = "printCopies", default = "default_print_copies")]
+    pub print_copies: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DeliveryMethod {
+    #[serde(rename = "email")]
+    Email,
+    #[serde(rename = "print")]
+    Print,
+    #[serde(rename = "qr")]
+    Qr,
+    #[serde(rename = "discard")]
+    Discard,
+}
+
+/// How strips are laid out on the paper when printing. Sizes are in inches.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct PrintSheet {
+    #[serde(rename = "paperWidth")]
+    pub paper_width: f32,
+    #[serde(rename = "paperHeight")]
+    pub paper_height: f32,
+    pub dpi: f32,
+    /// How many strips to put on one sheet.
+    pub copies: u32,
+    #[serde(rename = "cutMarks", default)]
+    pub cut_marks: bool,
+    #[serde(default)]
+    pub bleed: f32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub(crate) struct Template {
+    /// The unit used for the size of the template and its frames.
+    #[serde(default)]
+    pub units: TemplateUnits,
+    /// The resolution of the rendered strip. Pixel templates are rendered at
+    /// their own size, but this still sets the page size of PDF output.
+    #[serde(default = "default_template_dpi")]
+    pub dpi: f32,
     pub width: f32,
     pub height: f32,
     pub frames: Vec<Frame>,
+    /// Path to a PNG drawn on top of the photos. Its transparent areas act as
+    /// windows the photos show through.
+    #[serde(default)]
+    pub overlay: Option<String>,
+    /// Text stamped onto the strip, above the photos and the overlay.
+    #[serde(default)]
+    pub texts: Vec<TextElement>,
+}
+
+impl Template {
+    /// How many pixels of the rendered strip one template unit takes up.
+    pub fn pixels_per_unit(&self) -> f32 {
+        match self.units {
+            TemplateUnits::Pixels => 1.0,
+            TemplateUnits::Millimetres => self.dpi / 25.4,
+            TemplateUnits::Inches => self.dpi,
+        }
+    }
+
+    /// The size of the rendered strip in pixels. Strips from pixel templates
+    /// are rendered at the size of the template image instead.
+    pub fn output_size(&self) -> (u32, u32) {
+        let scale = self.pixels_per_unit();
+        (
+            (self.width * scale).round() as u32,
+            (self.height * scale).round() as u32,
+        )
+    }
+}
+
+/// A line (or lines) of text on the template. `content` can contain the
+/// placeholders `{name}`, `{date}`, `{time}` and `{session_id}`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct TextElement {
+    pub content: String,
+    /// Path to a TrueType or OpenType font file.
+    pub font: String,
+    /// The point the text is aligned and rotated around, in template units.
+    /// `y` is the top of the first line.
+    pub x: f32,
+    pub y: f32,
+    /// Font size in template units.
+    pub size: f32,
+    /// RGBA colour.
+    #[serde(default = "default_text_color")]
+    pub color: [u8; 4],
+    #[serde(default)]
+    pub align: TextAlign,
+    /// Clockwise rotation in degrees.
+    #[serde(default)]
+    pub rotation: f32,
+}
+
+fn default_text_color() -> [u8; 4] {
+    [0, 0, 0, 255]
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum TextAlign {
+    #[default]
+    #[serde(rename = "left")]
+    Left,
+    #[serde(rename = "center")]
+    Center,
+    #[serde(rename = "right")]
+    Right,
+}
+
+fn default_template_dpi() -> f32 {
+    300.0
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum TemplateUnits {
+    #[default]
+    #[serde(rename = "px")]
+    Pixels,
+    #[serde(rename = "mm")]
+    Millimetres,
+    #[serde(rename = "in")]
+    Inches,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -80,4 +738,23 @@ pub(crate) struct Frame {
     pub y: f32,
     pub width: f32,
     pub height: f32,
+    #[serde(default)]
+    pub shape: Option<FrameShape>,
+}
+
+/// The shape a photo is cut to in the rendered strip. Without one, photos are
+/// plain rectangles.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub(crate) enum FrameShape {
+    /// Rounded corners, with the radius in template units.
+    #[serde(rename = "rounded")]
+    Rounded { radius: f32 },
+    /// An ellipse filling the frame.
+    #[serde(rename = "ellipse")]
+    Ellipse,
+    /// A grayscale image stretched over the frame, where black is
+    /// transparent and white is opaque.
+    #[serde(rename = "mask")]
+    Mask { path: String },
 }
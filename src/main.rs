@@ -13,8 +13,10 @@ use iced::{Alignment, Color, Element, Length, Settings};
 pub fn main() -> iced::Result {
     let icon = image::load_from_memory(include_bytes!("../assets/icon.png"))
         .expect("failed to decode application icon");
-    let config =
+    let mut config =
         config::Config::new(include_str!("../assets/config.json")).expect("failed to read config");
+    utils::camera_controls::load_saved_camera_controls(&mut config);
+    utils::digital_zoom::load_saved_digital_zoom(&mut config);
     PhotoBooth::run(Settings {
         window: window::Settings {
             icon: Some(
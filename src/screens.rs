@@ -3,33 +3,45 @@ use iced::{Element, Subscription};
 use crate::config::Config;
 
 mod alert_screen;
+mod camera_controls_screen;
 mod camera_screen;
 mod config_screen;
+mod delivery_screen;
 mod email_screen;
 mod error_screen;
 mod generation_screen;
+mod printing_screen;
+mod qr_screen;
 mod sending_screen;
 
 #[derive(Debug, Clone)]
 pub enum ScreenMessage {
     TransitionToScreen(ScreenFlags),
     AlertScreenMessage(alert_screen::AlertScreenMessage),
+    CameraControlsScreenMessage(camera_controls_screen::CameraControlsScreenMessage),
     CameraScreenMessage(camera_screen::CameraScreenMessage),
     ConfigScreenMessage(config_screen::ConfigScreenMessage),
+    DeliveryScreenMessage(delivery_screen::DeliveryScreenMessage),
     EmailScreenMessage(email_screen::EmailScreenMessage),
     ErrorScreenMessage(error_screen::ErrorScreenMessage),
     GenerationScreenMessage(generation_screen::GenerationScreenMessage),
+    PrintingScreenMessage(printing_screen::PrintingScreenMessage),
+    QrScreenMessage(qr_screen::QrScreenMessage),
     SendingScreenMessage(sending_screen::SendingScreenMessage),
 }
 
 #[derive(Debug, Clone)]
 pub enum ScreenFlags {
     AlertScreenFlags(alert_screen::AlertScreenFlags),
+    CameraControlsScreenFlags(camera_controls_screen::CameraControlsScreenFlags),
     CameraScreenFlags(camera_screen::CameraScreenFlags),
     ConfigScreenFlags(config_screen::ConfigScreenFlags),
+    DeliveryScreenFlags(delivery_screen::DeliveryScreenFlags),
     EmailScreenFlags(email_screen::EmailScreenFlags),
     ErrorScreenFlags(error_screen::ErrorScreenFlags),
     GenerationScreenFlags(generation_screen::GenerationScreenFlags),
+    PrintingScreenFlags(printing_screen::PrintingScreenFlags),
+    QrScreenFlags(qr_screen::QrScreenFlags),
     SendingScreenFlags(sending_screen::SendingScreenFlags),
 }
 
@@ -43,6 +55,13 @@ impl Into<(Screen, Option<ScreenMessage>)> for ScreenFlags {
                     message.map(ScreenMessage::CameraScreenMessage),
                 )
             }
+            ScreenFlags::CameraControlsScreenFlags(flags) => {
+                let (screen, message) = camera_controls_screen::CameraControlsScreen::new(flags);
+                (
+                    Screen::CameraControlsScreen(screen),
+                    message.map(ScreenMessage::CameraControlsScreenMessage),
+                )
+            }
             ScreenFlags::ConfigScreenFlags(flags) => {
                 let (screen, message) = config_screen::ConfigScreen::new(flags);
                 (
@@ -78,6 +97,27 @@ impl Into<(Screen, Option<ScreenMessage>)> for ScreenFlags {
                     message.map(ScreenMessage::ErrorScreenMessage),
                 )
             }
+            ScreenFlags::DeliveryScreenFlags(flags) => {
+                let (screen, message) = delivery_screen::DeliveryScreen::new(flags);
+                (
+                    Screen::DeliveryScreen(screen),
+                    message.map(ScreenMessage::DeliveryScreenMessage),
+                )
+            }
+            ScreenFlags::PrintingScreenFlags(flags) => {
+                let (screen, message) = printing_screen::PrintingScreen::new(flags);
+                (
+                    Screen::PrintingScreen(screen),
+                    message.map(ScreenMessage::PrintingScreenMessage),
+                )
+            }
+            ScreenFlags::QrScreenFlags(flags) => {
+                let (screen, message) = qr_screen::QrScreen::new(flags);
+                (
+                    Screen::QrScreen(screen),
+                    message.map(ScreenMessage::QrScreenMessage),
+                )
+            }
             ScreenFlags::SendingScreenFlags(flags) => {
                 let (screen, message) = sending_screen::SendingScreen::new(flags);
                 (
@@ -90,17 +130,24 @@ impl Into<(Screen, Option<ScreenMessage>)> for ScreenFlags {
 }
 
 pub fn initial_screen(config: Config) -> ScreenFlags {
-    ScreenFlags::ConfigScreenFlags(config_screen::ConfigScreenFlags { config })
+    ScreenFlags::ConfigScreenFlags(config_screen::ConfigScreenFlags {
+        config,
+        camera: None,
+    })
 }
 
 #[derive(Debug)]
 pub enum Screen {
     AlertScreen(alert_screen::AlertScreen),
+    CameraControlsScreen(camera_controls_screen::CameraControlsScreen),
     CameraScreen(camera_screen::CameraScreen),
     ConfigScreen(config_screen::ConfigScreen),
+    DeliveryScreen(delivery_screen::DeliveryScreen),
     EmailScreen(email_screen::EmailScreen),
     ErrorScreen(error_screen::ErrorScreen),
     GenerationScreen(generation_screen::GenerationScreen),
+    PrintingScreen(printing_screen::PrintingScreen),
+    QrScreen(qr_screen::QrScreen),
     SendingScreen(sending_screen::SendingScreen),
 }
 
@@ -126,6 +173,10 @@ impl Screen {
             (Screen::CameraScreen(screen), ScreenMessage::CameraScreenMessage(msg)) => {
                 ScreenUpdateOutcome::Command(screen.update(msg).map(|x| x.into()))
             }
+            (
+                Screen::CameraControlsScreen(screen),
+                ScreenMessage::CameraControlsScreenMessage(msg),
+            ) => ScreenUpdateOutcome::Command(screen.update(msg).map(|x| x.into())),
             (Screen::ConfigScreen(screen), ScreenMessage::ConfigScreenMessage(msg)) => {
                 ScreenUpdateOutcome::Command(screen.update(msg).map(|x| x.into()))
             }
@@ -141,6 +192,15 @@ impl Screen {
             (Screen::ErrorScreen(screen), ScreenMessage::ErrorScreenMessage(msg)) => {
                 ScreenUpdateOutcome::Command(screen.update(msg).map(|x| x.into()))
             }
+            (Screen::DeliveryScreen(screen), ScreenMessage::DeliveryScreenMessage(msg)) => {
+                ScreenUpdateOutcome::Command(screen.update(msg).map(|x| x.into()))
+            }
+            (Screen::PrintingScreen(screen), ScreenMessage::PrintingScreenMessage(msg)) => {
+                ScreenUpdateOutcome::Command(screen.update(msg).map(|x| x.into()))
+            }
+            (Screen::QrScreen(screen), ScreenMessage::QrScreenMessage(msg)) => {
+                ScreenUpdateOutcome::Command(screen.update(msg).map(|x| x.into()))
+            }
             (Screen::SendingScreen(screen), ScreenMessage::SendingScreenMessage(msg)) => {
                 ScreenUpdateOutcome::Command(screen.update(msg).map(|x| x.into()))
             }
@@ -154,11 +214,15 @@ impl Screen {
     pub fn subscription(&self) -> Subscription<ScreenMessage> {
         match self {
             Screen::AlertScreen(screen) => screen.subscription().map(|x| x.into()),
+            Screen::CameraControlsScreen(screen) => screen.subscription().map(|x| x.into()),
             Screen::CameraScreen(screen) => screen.subscription().map(|x| x.into()),
             Screen::ConfigScreen(screen) => screen.subscription().map(|x| x.into()),
+            Screen::DeliveryScreen(screen) => screen.subscription().map(|x| x.into()),
             Screen::EmailScreen(screen) => screen.subscription().map(|x| x.into()),
             Screen::ErrorScreen(screen) => screen.subscription().map(|x| x.into()),
             Screen::GenerationScreen(screen) => screen.subscription().map(|x| x.into()),
+            Screen::PrintingScreen(screen) => screen.subscription().map(|x| x.into()),
+            Screen::QrScreen(screen) => screen.subscription().map(|x| x.into()),
             Screen::SendingScreen(screen) => screen.subscription().map(|x| x.into()),
         }
     }
@@ -166,11 +230,15 @@ impl Screen {
     pub fn view(&self) -> Element<ScreenMessage> {
         match self {
             Screen::AlertScreen(screen) => screen.view().map(|x| x.into()),
+            Screen::CameraControlsScreen(screen) => screen.view().map(|x| x.into()),
             Screen::CameraScreen(screen) => screen.view().map(|x| x.into()),
             Screen::ConfigScreen(screen) => screen.view().map(|x| x.into()),
+            Screen::DeliveryScreen(screen) => screen.view().map(|x| x.into()),
             Screen::EmailScreen(screen) => screen.view().map(|x| x.into()),
             Screen::ErrorScreen(screen) => screen.view().map(|x| x.into()),
             Screen::GenerationScreen(screen) => screen.view().map(|x| x.into()),
+            Screen::PrintingScreen(screen) => screen.view().map(|x| x.into()),
+            Screen::QrScreen(screen) => screen.view().map(|x| x.into()),
             Screen::SendingScreen(screen) => screen.view().map(|x| x.into()),
         }
     }
@@ -0,0 +1,429 @@
+use iced::{
+    alignment,
+    widget::{
+        button, checkbox, container, pick_list, scrollable, slider, text, Column, Row, Space,
+    },
+    Alignment, Command, Element, Length,
+};
+use nokhwa::{
+    pixel_format::RgbAFormat,
+    utils::{ControlValueDescription, KnownCameraControl, RequestedFormat, RequestedFormatType},
+    Camera,
+};
+
+use crate::{
+    config::{CameraControlSetting, CameraControlValue, Config, CropStrategy},
+    utils::{
+        camera_controls::{
+            apply_camera_controls, control_key, control_setter, control_value,
+            default_control_value, save_camera_controls, set_control_value,
+        },
+        crop_focus::CropFocus,
+    },
+};
+
+use super::camera_screen::camera_feed::{CameraFeed, CameraMessage};
+
+/// Steps a slider is split into when the camera doesn't say.
+const FALLBACK_SLIDER_STEPS: f64 = 100.0;
+
+#[derive(Debug, Clone)]
+struct ControlState {
+    control: KnownCameraControl,
+    name: String,
+    /// What the camera last took.
+    description: ControlValueDescription,
+    /// The latest value sent to the camera that it hasn't taken yet, shown
+    /// in place of the current one.
+    pending: Option<CameraControlValue>,
+    /// Whether the value is saved and set whenever the camera is opened.
+    locked: bool,
+}
+
+pub(crate) struct CameraControlsScreen {
+    feed: CameraFeed,
+    config: Config,
+    index: nokhwa::utils::CameraIndex,
+    camera_name: String,
+    controls: Vec<ControlState>,
+    /// What happened to the last change, shown under the controls.
+    status: Option<String>,
+}
+
+impl std::fmt::Debug for CameraControlsScreen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CameraControlsScreen")
+            .field("camera_name", &self.camera_name)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum CameraControlsScreenMessage {
+    CameraFeedMessage(CameraMessage),
+    ValueChanged(usize, CameraControlValue),
+    LockToggled(usize, bool),
+    ResetPressed(usize),
+    ControlSet(usize, CameraControlValue, Result<(), String>),
+    SavePressed,
+    BackPressed,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct CameraControlsScreenFlags {
+    pub config: Config,
+    pub index: nokhwa::utils::CameraIndex,
+}
+
+impl Into<super::ScreenMessage> for CameraControlsScreenMessage {
+    fn into(self) -> super::ScreenMessage {
+        super::ScreenMessage::CameraControlsScreenMessage(self)
+    }
+}
+
+impl CameraControlsScreen {
+    /// Set control `i` on the camera to `value`, off the UI thread since the
+    /// camera might be busy taking a frame. It's pending until the camera
+    /// answers.
+    fn set_control(
+        &mut self,
+        i: usize,
+        value: CameraControlValue,
+    ) -> Command<super::ScreenMessage> {
+        let control = &mut self.controls[i];
+        let Some(setter) = control_setter(&control.description, value) else {
+            return Command::none();
+        };
+        control.pending = Some(value);
+        let known_control = control.control;
+        let camera = self.feed.camera();
+        Command::perform(
+            async move {
+                tokio::task::spawn_blocking(move || {
+                    camera
+                        .lock()
+                        .expect("failed to lock camera mutex")
+                        .set_camera_control(known_control, setter)
+                        .map_err(|e| e.to_string())
+                })
+                .await
+                .unwrap_or_else(|e| Err(e.to_string()))
+            },
+            move |result| CameraControlsScreenMessage::ControlSet(i, value, result).into(),
+        )
+    }
+
+    fn control_view(
+        &self,
+        i: usize,
+        control: &ControlState,
+    ) -> Element<'_, CameraControlsScreenMessage> {
+        let mut description = control.description.clone();
+        if let Some(pending) = control.pending {
+            set_control_value(&mut description, pending);
+        }
+        let input: Element<_> = match &description {
+            ControlValueDescription::IntegerRange {
+                min,
+                max,
+                value,
+                step,
+                ..
+            } => Row::new()
+                .push(
+                    slider(*min as f64..=*max as f64, *value as f64, move |value| {
+                        CameraControlsScreenMessage::ValueChanged(
+                            i,
+                            CameraControlValue::Integer(value.round() as i64),
+                        )
+                    })
+                    .step((*step).max(1) as f64),
+                )
+                .push(text(value).width(60))
+                .spacing(12)
+                .into(),
+            ControlValueDescription::FloatRange {
+                min,
+                max,
+                value,
+                step,
+                ..
+            } => Row::new()
+                .push(
+                    slider(*min..=*max, *value, move |value| {
+                        CameraControlsScreenMessage::ValueChanged(
+                            i,
+                            CameraControlValue::Float(value),
+                        )
+                    })
+                    .step(if *step > 0.0 {
+                        *step
+                    } else {
+                        (max - min) / FALLBACK_SLIDER_STEPS
+                    }),
+                )
+                .push(text(format!("{:.2}", value)).width(60))
+                .spacing(12)
+                .into(),
+            ControlValueDescription::Boolean { value, .. } => checkbox("On", *value)
+                .on_toggle(move |value| {
+                    CameraControlsScreenMessage::ValueChanged(i, CameraControlValue::Boolean(value))
+                })
+                .into(),
+            ControlValueDescription::Enum {
+                value, possible, ..
+            } => pick_list(possible.clone(), Some(*value), move |value| {
+                CameraControlsScreenMessage::ValueChanged(i, CameraControlValue::Integer(value))
+            })
+            .into(),
+            // without a range there's nothing sensible to drag between
+            description => text(match control_value(description) {
+                Some(CameraControlValue::Integer(value)) => value.to_string(),
+                Some(CameraControlValue::Float(value)) => format!("{:.2}", value),
+                Some(CameraControlValue::Boolean(value)) => value.to_string(),
+                None => "Can't be changed here".to_string(),
+            })
+            .into(),
+        };
+        Column::new()
+            .push(
+                Row::new()
+                    .push(text(&control.name).size(18).width(Length::Fill))
+                    .push(checkbox("Lock", control.locked).on_toggle_maybe(
+                        control_value(&control.description).map(|_| {
+                            move |locked| CameraControlsScreenMessage::LockToggled(i, locked)
+                        }),
+                    ))
+                    .push(
+                        button(text("Reset").size(14))
+                            .on_press_maybe(
+                                default_control_value(&control.description)
+                                    .map(|_| CameraControlsScreenMessage::ResetPressed(i)),
+                            )
+                            .padding([2, 8]),
+                    )
+                    .spacing(12)
+                    .align_items(Alignment::Center),
+            )
+            .push(input)
+            .spacing(6)
+            .into()
+    }
+}
+
+impl super::Screenish for CameraControlsScreen {
+    type Message = CameraControlsScreenMessage;
+    type Flags = CameraControlsScreenFlags;
+    fn new(flags: CameraControlsScreenFlags) -> (Self, Option<CameraControlsScreenMessage>) {
+        let requested =
+            RequestedFormat::new::<RgbAFormat>(RequestedFormatType::AbsoluteHighestFrameRate);
+        let mut camera = Camera::new(flags.index.clone(), requested).unwrap();
+        camera.open_stream().unwrap();
+        let camera_name = camera.info().human_name();
+        let locked = flags
+            .config
+            .camera_controls
+            .get(&camera_name)
+            .cloned()
+            .unwrap_or_default();
+        apply_camera_controls(&mut camera, &locked);
+        let (controls, status) = match camera.camera_controls() {
+            Ok(controls) => (
+                controls
+                    .into_iter()
+                    .map(|control| ControlState {
+                        control: control.control(),
+                        name: control.name().to_string(),
+                        description: control.description().clone(),
+                        pending: None,
+                        locked: locked
+                            .iter()
+                            .any(|setting| setting.control == control_key(control.control())),
+                    })
+                    .collect(),
+                None,
+            ),
+            Err(e) => (
+                vec![],
+                Some(format!("Failed to read the camera's controls: {}", e)),
+            ),
+        };
+        let (mut feed, feed_command) = CameraFeed::new(
+            camera,
+            16.into(),
+            flags.config.mirror_preview,
+            None,
+            1,
+            CropFocus::new(&CropStrategy::Centre),
+        );
+        feed.set_rotation(flags.config.camera_rotation);
+        (
+            CameraControlsScreen {
+                feed,
+                config: flags.config,
+                index: flags.index,
+                camera_name,
+                controls,
+                status,
+            },
+            feed_command.map(CameraControlsScreenMessage::CameraFeedMessage),
+        )
+    }
+    fn update(&mut self, message: CameraControlsScreenMessage) -> Command<super::ScreenMessage> {
+        match message {
+            CameraControlsScreenMessage::CameraFeedMessage(msg) => self
+                .feed
+                .update(msg)
+                .map(CameraControlsScreenMessage::CameraFeedMessage)
+                .map(super::ScreenMessage::CameraControlsScreenMessage),
+            CameraControlsScreenMessage::ValueChanged(i, value) => self.set_control(i, value),
+            // unlocking only stops the value being saved, since there's no
+            // telling which control turns the camera's automatic mode back on
+            CameraControlsScreenMessage::LockToggled(i, locked) => {
+                self.controls[i].locked = locked;
+                Command::none()
+            }
+            CameraControlsScreenMessage::ResetPressed(i) => {
+                match default_control_value(&self.controls[i].description) {
+                    Some(value) => self.set_control(i, value),
+                    None => Command::none(),
+                }
+            }
+            CameraControlsScreenMessage::ControlSet(i, value, result) => {
+                let control = &mut self.controls[i];
+                // a newer value might still be on its way
+                if control.pending == Some(value) {
+                    control.pending = None;
+                }
+                self.status = match result {
+                    Ok(()) => {
+                        // changing a control locks it, since that's usually
+                        // the point
+                        set_control_value(&mut control.description, value);
+                        control.locked = true;
+                        None
+                    }
+                    Err(e) => Some(format!("The camera didn't take that: {}", e)),
+                };
+                Command::none()
+            }
+            CameraControlsScreenMessage::SavePressed => {
+                let settings: Vec<CameraControlSetting> = self
+                    .controls
+                    .iter()
+                    .filter(|control| control.locked)
+                    .filter_map(|control| {
+                        control_value(&control.description).map(|value| CameraControlSetting {
+                            control: control_key(control.control),
+                            value,
+                        })
+                    })
+                    .collect();
+                self.status = Some(
+                    match save_camera_controls(
+                        &self.config.camera_controls_file,
+                        &self.camera_name,
+                        settings.clone(),
+                    ) {
+                        Ok(()) => format!(
+                            "Saved {} locked controls for {}",
+                            settings.len(),
+                            self.camera_name
+                        ),
+                        Err(e) => format!(
+                            "Failed to save to {:?}: {}",
+                            self.config.camera_controls_file, e
+                        ),
+                    },
+                );
+                // the booth uses them from now on either way
+                self.config
+                    .camera_controls
+                    .insert(self.camera_name.clone(), settings);
+                Command::none()
+            }
+            CameraControlsScreenMessage::BackPressed => {
+                let flags = super::config_screen::ConfigScreenFlags {
+                    config: self.config.clone(),
+                    camera: Some(self.index.clone()),
+                };
+                Command::perform(async {}, |_| {
+                    super::ScreenMessage::TransitionToScreen(super::ScreenFlags::ConfigScreenFlags(
+                        flags,
+                    ))
+                })
+            }
+        }
+    }
+    fn view(&self) -> Element<CameraControlsScreenMessage> {
+        let controls = self
+            .controls
+            .iter()
+            .enumerate()
+            .fold(Column::new().spacing(16), |column, (i, control)| {
+                column.push(self.control_view(i, control))
+            })
+            .push_maybe(if self.controls.is_empty() {
+                Some(text("This camera doesn't have any controls."))
+            } else {
+                None
+            })
+            .padding([0, 16, 0, 0]);
+        container(
+            Row::new()
+                .push(
+                    self.feed
+                        .view()
+                        .width(Length::FillPortion(3))
+                        .height(Length::Fill),
+                )
+                .push(
+                    Column::new()
+                        .push(text(format!("{} settings", self.camera_name)).size(24))
+                        .push(scrollable(controls).height(Length::Fill))
+                        .push_maybe(self.status.as_ref().map(|status| text(status).size(16)))
+                        .push(
+                            Row::new()
+                                .push(
+                                    button(
+                                        text("Back")
+                                            .size(18)
+                                            .horizontal_alignment(alignment::Horizontal::Center),
+                                    )
+                                    .on_press(CameraControlsScreenMessage::BackPressed)
+                                    .padding(6),
+                                )
+                                .push(Space::with_width(Length::Fill))
+                                .push(
+                                    button(
+                                        text("Save locked controls")
+                                            .size(18)
+                                            .horizontal_alignment(alignment::Horizontal::Center),
+                                    )
+                                    .on_press(CameraControlsScreenMessage::SavePressed)
+                                    .padding(6),
+                                ),
+                        )
+                        .spacing(12)
+                        .width(Length::FillPortion(2)),
+                )
+                .spacing(20),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .padding(20)
+        .into()
+    }
+
+    fn subscription(&self) -> iced::Subscription<CameraControlsScreenMessage> {
+        self.feed
+            .subscription()
+            .map(CameraControlsScreenMessage::CameraFeedMessage)
+    }
+}
+
+impl Into<super::Screen> for CameraControlsScreen {
+    fn into(self) -> super::Screen {
+        super::Screen::CameraControlsScreen(self)
+    }
+}
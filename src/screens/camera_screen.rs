@@ -1,11 +1,27 @@
-mod camera_feed;
+pub(super) mod camera_feed;
 mod element_strip_renderer;
+mod template_guide;
 
-use std::time::Duration;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use crate::{
-    config::Config,
-    utils::{circle::circle, template_image::TEMPLATE_IMAGE},
+    config::{Config, DigitalZoom},
+    utils::{
+        backgrounds::{load_backgrounds, LoadedBackground},
+        camera_controls::apply_camera_controls,
+        chroma_key::{cover, ChromaKeyer},
+        circle::circle,
+        crop::{crop_rect, zoom_rect},
+        crop_focus::CropFocus,
+        digital_zoom::save_digital_zoom,
+        looks::{load_looks, LoadedLook},
+        sharpness::laplacian_variance,
+        template_image::TEMPLATE_IMAGE,
+        text_stamp::{stamp_texts, StampValues},
+    },
 };
 use anim::{Animation, Timeline};
 use camera_feed::{CameraFeed, CameraMessage};
@@ -15,17 +31,29 @@ use iced::{
     Alignment, Color, Element, Length,
 };
 use iced_aw::floating_element;
-use image::Rgba;
+use image::RgbaImage;
 use nokhwa::{
     pixel_format::RgbAFormat,
     utils::{RequestedFormat, RequestedFormatType},
     Camera,
 };
 
-use self::element_strip_renderer::element_strip_renderer;
+use self::{
+    element_strip_renderer::element_strip_renderer,
+    template_guide::{guide_bases, template_guides},
+};
 
 const COUNTER_RADIUS: f32 = 80.0;
 const GET_READY_FONT_SIZE: f32 = 60.0;
+/// How many of the latest camera frames are kept to pick photos from, at
+/// least. A burst keeps as many as it needs.
+const RECENT_FRAMES: usize = 8;
+/// How far the digital zoom moves for each arrow key press, as a fraction of
+/// the camera's width or height.
+const PAN_STEP: f32 = 0.02;
+/// How much the digital zoom changes for each press of [+] or [-].
+const ZOOM_STEP: f32 = 1.1;
+const MAX_ZOOM: f32 = 4.0;
 
 fn get_ready_animation() -> impl Animation<Item = f32> {
     #[cfg(not(feature = "fast_animations"))]
@@ -123,6 +151,73 @@ fn frame_size_animation() -> impl Animation<Item = f32> {
     ])
 }
 
+/// The template texts rendered with `stamp_values` over the whole template,
+/// if it has any.
+fn text_image(config: &Config, stamp_values: &StampValues) -> Option<RgbaImage> {
+    if config.template.texts.is_empty() {
+        return None;
+    }
+    let (width, height) = config.template.output_size();
+    let mut text_image = RgbaImage::new(width, height);
+    stamp_texts(&mut text_image, &config.template, stamp_values);
+    Some(text_image)
+}
+
+/// The images shown above the captured frames: the template overlay and the
+/// rendered template texts.
+fn overlay_image_handles(config: &Config, text_image: Option<&RgbaImage>) -> Vec<Handle> {
+    config
+        .template
+        .overlay
+        .as_ref()
+        .map(Handle::from_path)
+        .into_iter()
+        .chain(text_image.map(|text_image| {
+            Handle::from_pixels(text_image.width(), text_image.height(), text_image.to_vec())
+        }))
+        .collect()
+}
+
+/// The parts of the template guides that are the same for every session, if
+/// they're turned on: the frames' shapes and the template overlay.
+fn template_guide_bases(config: &Config) -> Option<Vec<RgbaImage>> {
+    config.template_guide.as_ref()?;
+    let overlay = config
+        .template
+        .overlay
+        .as_ref()
+        .and_then(|path| match image::open(path) {
+            Ok(overlay) => Some(overlay.into_rgba8()),
+            Err(e) => {
+                eprintln!(
+                    "warning: failed to open the overlay {:?}, the template guide won't show it: {}",
+                    path, e
+                );
+                None
+            }
+        });
+    Some(guide_bases(&config.template, overlay.as_ref()))
+}
+
+/// The template guides for every frame, if they're turned on, made from
+/// `bases` with `text_image` on top.
+fn guide_handles(
+    config: &Config,
+    bases: &[RgbaImage],
+    text_image: Option<&RgbaImage>,
+) -> Vec<Handle> {
+    let Some(settings) = &config.template_guide else {
+        return vec![];
+    };
+    template_guides(
+        &config.template,
+        bases,
+        text_image,
+        settings,
+        config.mirror_preview != config.mirror_output,
+    )
+}
+
 enum FrameCaptureSequenceState {
     Counter(u16),
     Snap,
@@ -139,7 +234,17 @@ pub(crate) struct CameraScreen {
     feed: CameraFeed,
     config: Config,
     index: nokhwa::utils::CameraIndex,
-    captured_frames: Vec<(image::ImageBuffer<Rgba<u8>, Vec<u8>>, Handle)>,
+    captured_frames: Vec<(Arc<RgbaImage>, Handle)>,
+    looks: Vec<LoadedLook>,
+    /// Which of the `looks` the guest picked.
+    look: usize,
+    /// Set when there's a chroma key with at least one background.
+    chroma_keyer: Option<ChromaKeyer>,
+    backgrounds: Vec<LoadedBackground>,
+    /// Which of the `backgrounds` the guest picked.
+    background: usize,
+    /// Whether the operator is adjusting the digital zoom.
+    admin: bool,
 
     capture_sequence_state: CaptureSequenceState,
 
@@ -149,6 +254,15 @@ pub(crate) struct CameraScreen {
     frame_size_timeline: Timeline<f32>,
 
     frame_image_handle: Handle,
+    /// Images shown above the captured frames: the template overlay and the
+    /// rendered template texts.
+    overlay_image_handles: Vec<Handle>,
+    /// The parts of the template guides that don't change between sessions,
+    /// made along with the first session's guides.
+    guide_bases: Option<Arc<Vec<RgbaImage>>>,
+    /// What the template will hide of each frame, shown over the preview.
+    guides: Vec<Handle>,
+    stamp_values: StampValues,
 }
 
 #[derive(Clone, Debug)]
@@ -165,9 +279,23 @@ impl std::fmt::Debug for CameraScreen {
 
 #[derive(Debug, Clone)]
 pub enum CameraScreenMessage {
+    /// Sent once when the screen opens, with the camera feed's first message.
+    Started(Option<CameraMessage>),
     CameraFeedMessage(CameraMessage),
+    /// The overlay image handles and template guides for a session, with its
+    /// texts rendered, and the guide bases they were made from.
+    DecorationsRendered(Vec<Handle>, Vec<Handle>, Option<Arc<Vec<RgbaImage>>>),
     CaptureButtonPressed,
-    ImageCaptured(image::ImageBuffer<Rgba<u8>, Vec<u8>>),
+    PreviousLookPressed,
+    NextLookPressed,
+    PreviousBackgroundPressed,
+    NextBackgroundPressed,
+    AdminModePressed,
+    PanPressed(f32, f32),
+    ZoomPressed(f32),
+    ZoomResetPressed,
+    ImageCaptured(Arc<RgbaImage>, Handle),
+    CaptureFailed(String),
     Tick,
 }
 
@@ -185,22 +313,62 @@ impl super::Screenish for CameraScreen {
             RequestedFormat::new::<RgbAFormat>(RequestedFormatType::AbsoluteHighestFrameRate);
         let mut camera = Camera::new(flags.index.clone(), requested).unwrap();
         camera.open_stream().unwrap();
-        let mut aspect_ratio =
-            Some(flags.config.template.frames[0].width / flags.config.template.frames[0].height);
-        for frame in flags.config.template.frames.iter().skip(1) {
-            if Some(frame.width / frame.height) != aspect_ratio {
-                aspect_ratio = None;
-                break;
-            }
+        // put back the controls the operator locked for this camera
+        if let Some(settings) = flags
+            .config
+            .camera_controls
+            .get(&camera.info().human_name())
+        {
+            apply_camera_controls(&mut camera, settings);
         }
-        let (feed, feed_command) =
-            CameraFeed::new(camera, 48.into(), flags.config.mirror_preview, aspect_ratio);
+        // the preview is cropped like the frame that's about to be taken
+        let first_frame = &flags.config.template.frames[0];
+        let (mut feed, feed_command) = CameraFeed::new(
+            camera,
+            48.into(),
+            flags.config.mirror_preview,
+            Some(first_frame.width / first_frame.height),
+            RECENT_FRAMES.max(flags.config.burst_count as usize),
+            CropFocus::new(&flags.config.crop_strategy),
+        );
+        feed.set_rotation(flags.config.camera_rotation);
+        let looks = load_looks(&flags.config.looks);
+        feed.set_look(looks[0].lut.clone());
+        let backgrounds = flags
+            .config
+            .chroma_key
+            .as_ref()
+            .map(|chroma_key| load_backgrounds(&chroma_key.backgrounds))
+            .unwrap_or_default();
+        let chroma_keyer = flags
+            .config
+            .chroma_key
+            .as_ref()
+            .filter(|_| !backgrounds.is_empty())
+            .map(|chroma_key| {
+                ChromaKeyer::new(
+                    chroma_key.key_color,
+                    chroma_key.tolerance,
+                    chroma_key.softness,
+                    chroma_key.spill_suppression,
+                )
+            });
+        feed.set_chroma_key(chroma_keyer.map(|keyer| (keyer, backgrounds[0].image.clone())));
+        let digital_zoom = flags.config.digital_zoom;
+        feed.set_digital_zoom(digital_zoom.zoom, (digital_zoom.x, digital_zoom.y));
+        let stamp_values = StampValues::new(&flags.config);
         (
             CameraScreen {
                 feed,
                 config: flags.config.clone(),
                 index: flags.index,
                 captured_frames: vec![],
+                looks,
+                look: 0,
+                chroma_keyer,
+                backgrounds,
+                background: 0,
+                admin: false,
 
                 capture_sequence_state: CaptureSequenceState::None,
 
@@ -210,12 +378,39 @@ impl super::Screenish for CameraScreen {
                 snap_timeline: snap_animation().to_timeline(),
 
                 frame_image_handle: Handle::from_memory(TEMPLATE_IMAGE),
+                // the texts are rendered in the background once the screen
+                // has started
+                overlay_image_handles: overlay_image_handles(&flags.config, None),
+                guide_bases: None,
+                guides: vec![],
+                stamp_values,
             },
-            feed_command.map(CameraScreenMessage::CameraFeedMessage),
+            Some(CameraScreenMessage::Started(feed_command)),
         )
     }
     fn update(&mut self, message: CameraScreenMessage) -> iced::Command<super::ScreenMessage> {
         match message {
+            CameraScreenMessage::Started(feed_message) => iced::Command::batch([
+                match feed_message {
+                    Some(feed_message) => self
+                        .feed
+                        .update(feed_message)
+                        .map(CameraScreenMessage::CameraFeedMessage)
+                        .map(super::ScreenMessage::CameraScreenMessage),
+                    None => iced::Command::none(),
+                },
+                self.render_decorations(),
+            ]),
+            CameraScreenMessage::DecorationsRendered(
+                overlay_image_handles,
+                guides,
+                guide_bases,
+            ) => {
+                self.overlay_image_handles = overlay_image_handles;
+                self.guides = guides;
+                self.guide_bases = guide_bases;
+                iced::Command::none()
+            }
             CameraScreenMessage::CameraFeedMessage(msg) => self
                 .feed
                 .update(msg)
@@ -252,26 +447,125 @@ impl super::Screenish for CameraScreen {
                                             );
                                         self.snap_timeline.begin();
 
-                                        // capture the frame
+                                        // capture the frame from when the counter ran
+                                        // out, rather than whenever the task gets to it
+                                        let shutter_offset = Duration::from_millis(
+                                            self.config.shutter_offset.unsigned_abs(),
+                                        );
+                                        let shutter = if self.config.shutter_offset < 0 {
+                                            Instant::now()
+                                                .checked_sub(shutter_offset)
+                                                .unwrap_or_else(Instant::now)
+                                        } else {
+                                            Instant::now() + shutter_offset
+                                        };
                                         // everything important inside feed is Arc'd
                                         // so perf is fine
                                         let mut feed = self.feed.clone();
                                         let mirror_output = self.config.mirror_output;
+                                        let burst_count = self.config.burst_count;
+                                        let crop_focus = feed.crop_focus();
+                                        let digital_zoom = self.config.digital_zoom;
+                                        let lut = self.looks[self.look].lut.clone();
+                                        let chroma_key = self.chroma_key();
+                                        let filter = self.config.resample_filter.filter_type();
+                                        let slot_aspect_ratio = self
+                                            .config
+                                            .template
+                                            .frames
+                                            .get(self.captured_frames.len())
+                                            .map(|frame| frame.width / frame.height);
                                         return iced::Command::perform(
                                             async move {
                                                 tokio::task::spawn_blocking(move || {
-                                                    let mut framed = feed.frame();
+                                                    let frames = feed.frames_near(
+                                                        shutter,
+                                                        burst_count as usize,
+                                                    )?;
+                                                    let framed = if burst_count > 1 {
+                                                        // keep the least blurry frame
+                                                        frames
+                                                            .into_iter()
+                                                            .map(|frame| {
+                                                                (laplacian_variance(&frame), frame)
+                                                            })
+                                                            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+                                                            .map(|(_, frame)| frame)
+                                                    } else {
+                                                        frames.into_iter().next()
+                                                    };
+                                                    let mut framed = framed.ok_or(
+                                                        "the camera hasn't taken any frames",
+                                                    )?;
+                                                    // before mirroring, since the zoom is
+                                                    // worked out on the camera's frames
+                                                    if digital_zoom.zoom > 1.0 {
+                                                        let (left, top, width, height) = zoom_rect(
+                                                            framed.width(),
+                                                            framed.height(),
+                                                            digital_zoom.zoom,
+                                                            (digital_zoom.x, digital_zoom.y),
+                                                        );
+                                                        framed = image::imageops::crop_imm(
+                                                            &framed, left, top, width, height,
+                                                        )
+                                                        .to_image();
+                                                    }
                                                     if mirror_output {
                                                         image::imageops::flip_horizontal_in_place(
                                                             &mut framed,
                                                         )
                                                     }
-                                                    framed
+                                                    // after mirroring, so the background
+                                                    // isn't mirrored
+                                                    if let Some((keyer, background)) = chroma_key {
+                                                        let background = cover(
+                                                            &background,
+                                                            framed.width(),
+                                                            framed.height(),
+                                                            filter,
+                                                        );
+                                                        keyer.composite(&mut framed, &background);
+                                                    }
+                                                    // show the frame in the strip cropped
+                                                    // the same way it will be printed
+                                                    let (left, top, width, height) = crop_rect(
+                                                        framed.width(),
+                                                        framed.height(),
+                                                        slot_aspect_ratio,
+                                                        if mirror_output {
+                                                            crop_focus.mirrored_focus(&framed)
+                                                        } else {
+                                                            crop_focus.focus(&framed)
+                                                        },
+                                                    );
+                                                    let mut thumbnail = image::imageops::crop_imm(
+                                                        &framed, left, top, width, height,
+                                                    )
+                                                    .to_image();
+                                                    if let Some(lut) = lut {
+                                                        lut.apply(&mut thumbnail);
+                                                    }
+                                                    let handle = Handle::from_pixels(
+                                                        thumbnail.width(),
+                                                        thumbnail.height(),
+                                                        thumbnail.into_raw(),
+                                                    );
+                                                    Ok((Arc::new(framed), handle))
                                                 })
                                                 .await
-                                                .unwrap()
+                                                .unwrap_or_else(|e| Err(e.to_string()))
+                                            },
+                                            |result| match result {
+                                                Ok((image, handle)) => {
+                                                    CameraScreenMessage::ImageCaptured(
+                                                        image, handle,
+                                                    )
+                                                }
+                                                Err(reason) => {
+                                                    CameraScreenMessage::CaptureFailed(reason)
+                                                }
                                             },
-                                            CameraScreenMessage::ImageCaptured,
                                         )
                                         .map(super::ScreenMessage::CameraScreenMessage);
                                     }
@@ -304,18 +598,23 @@ impl super::Screenish for CameraScreen {
                                         let config = self.config.clone();
                                         let captured_frames = self
                                             .captured_frames
-                                            .clone()
-                                            .into_iter()
-                                            .map(|frame| frame.0)
+                                            .iter()
+                                            .map(|(frame, _)| frame.clone())
                                             .collect();
                                         let index = self.index.clone();
+                                        let stamp_values = self.stamp_values.clone();
+                                        let look = self.looks[self.look].lut.clone();
+                                        let crop_focus = self.feed.crop_focus();
                                         return iced::Command::perform(
                                             async {
                                                 super::ScreenFlags::GenerationScreenFlags(
                                                     super::generation_screen::GenerationScreenFlags {
                                                         config,
                                                         captured_frames,
-                                                        index
+                                                        look,
+                                                        index,
+                                                        stamp_values,
+                                                        crop_focus,
                                                     },
                                                 )
                                             },
@@ -329,18 +628,110 @@ impl super::Screenish for CameraScreen {
                 }
                 iced::Command::none()
             }
-            CameraScreenMessage::ImageCaptured(image) => {
-                self.captured_frames.push((
-                    image.clone(), // bad for performance, but we need to give a handle to iced to render the preview...
-                    Handle::from_pixels(image.width(), image.height(), image.into_raw()),
-                ));
+            CameraScreenMessage::ImageCaptured(image, handle) => {
+                self.captured_frames.push((image, handle));
                 self.frame_size_timeline.begin();
+                if let Some(next_frame) =
+                    self.config.template.frames.get(self.captured_frames.len())
+                {
+                    self.feed
+                        .set_aspect_ratio(Some(next_frame.width / next_frame.height));
+                }
+                iced::Command::none()
+            }
+            CameraScreenMessage::CaptureFailed(reason) => {
+                eprintln!("failed to capture a photo: {}", reason);
+                let flags = super::error_screen::ErrorScreenFlags {
+                    config: self.config.clone(),
+                    index: self.index.clone(),
+
+                    error_title: "Something went wrong".to_string(),
+                    error_content: format!(
+                        "We couldn't take your photo. Error message: {}",
+                        reason
+                    ),
+                };
+                iced::Command::perform(async {}, |_| {
+                    super::ScreenMessage::TransitionToScreen(super::ScreenFlags::ErrorScreenFlags(
+                        flags,
+                    ))
+                })
+            }
+            CameraScreenMessage::PreviousLookPressed | CameraScreenMessage::NextLookPressed => {
+                // the look can't change halfway through a strip
+                if matches!(self.capture_sequence_state, CaptureSequenceState::None) {
+                    let step = if matches!(message, CameraScreenMessage::NextLookPressed) {
+                        1
+                    } else {
+                        self.looks.len() - 1
+                    };
+                    self.look = (self.look + step) % self.looks.len();
+                    self.feed.set_look(self.looks[self.look].lut.clone());
+                }
+                iced::Command::none()
+            }
+            CameraScreenMessage::PreviousBackgroundPressed
+            | CameraScreenMessage::NextBackgroundPressed => {
+                if matches!(self.capture_sequence_state, CaptureSequenceState::None)
+                    && !self.backgrounds.is_empty()
+                {
+                    let step = if matches!(message, CameraScreenMessage::NextBackgroundPressed) {
+                        1
+                    } else {
+                        self.backgrounds.len() - 1
+                    };
+                    self.background = (self.background + step) % self.backgrounds.len();
+                    self.feed.set_chroma_key(self.chroma_key());
+                }
+                iced::Command::none()
+            }
+            CameraScreenMessage::AdminModePressed => {
+                if self.admin {
+                    self.admin = false;
+                    if let Err(e) =
+                        save_digital_zoom(&self.config.digital_zoom_file, self.config.digital_zoom)
+                    {
+                        eprintln!(
+                            "warning: failed to save the digital zoom to {:?}: {}",
+                            self.config.digital_zoom_file, e
+                        );
+                    }
+                } else if matches!(self.capture_sequence_state, CaptureSequenceState::None) {
+                    self.admin = true;
+                }
+                iced::Command::none()
+            }
+            CameraScreenMessage::PanPressed(dx, dy) => {
+                // follow the arrows on screen, even when the preview is mirrored
+                let dx = if self.config.mirror_preview { -dx } else { dx };
+                let digital_zoom = self.config.digital_zoom;
+                self.set_digital_zoom(
+                    digital_zoom.zoom,
+                    (digital_zoom.x + dx, digital_zoom.y + dy),
+                );
+                iced::Command::none()
+            }
+            CameraScreenMessage::ZoomPressed(factor) => {
+                let digital_zoom = self.config.digital_zoom;
+                self.set_digital_zoom(digital_zoom.zoom * factor, (digital_zoom.x, digital_zoom.y));
+                iced::Command::none()
+            }
+            CameraScreenMessage::ZoomResetPressed => {
+                self.set_digital_zoom(1.0, (0.5, 0.5));
                 iced::Command::none()
             }
             CameraScreenMessage::CaptureButtonPressed => {
+                let command = if matches!(self.capture_sequence_state, CaptureSequenceState::None) {
+                    // the screen might have been idle for a while, so the
+                    // session starts now
+                    self.stamp_values = StampValues::new(&self.config);
+                    self.render_decorations()
+                } else {
+                    iced::Command::none()
+                };
                 self.capture_sequence_state = CaptureSequenceState::GetReady;
                 self.get_ready_timeline.begin();
-                iced::Command::none()
+                command
             }
         }
     }
@@ -355,23 +746,68 @@ impl super::Screenish for CameraScreen {
                     .push(
                         floating_element(
                             Column::new()
-                                .push(self.feed.view().width(Length::Fill).height(Length::Fill))
-                                .push_maybe(
-                                    if matches!(
-                                        self.capture_sequence_state,
-                                        CaptureSequenceState::None
-                                    ) {
-                                        Some(
-                                            text("Press [Space] to start taking pictures!")
-                                                .size(34)
-                                                .vertical_alignment(
-                                                    iced::alignment::Vertical::Center,
-                                                ),
-                                        )
-                                    } else {
-                                        None
-                                    },
-                                )
+                                .push(self.preview())
+                                .push_maybe(if self.admin {
+                                    Some(
+                                        Column::new()
+                                            .push(
+                                                text(format!(
+                                                    "Zoom {:.1}x",
+                                                    self.config.digital_zoom.zoom
+                                                ))
+                                                .size(28),
+                                            )
+                                            .push(
+                                                text(
+                                                    "[Arrows] move  [+] [-] zoom  [0] reset  \
+                                                         [F2] save and exit",
+                                                )
+                                                .size(24),
+                                            )
+                                            .spacing(8)
+                                            .align_items(Alignment::Center),
+                                    )
+                                } else if matches!(
+                                    self.capture_sequence_state,
+                                    CaptureSequenceState::None
+                                ) {
+                                    Some(
+                                        Column::new()
+                                            .push_maybe(if self.looks.len() > 1 {
+                                                Some(
+                                                    text(format!(
+                                                        "[←]  {}  [→]",
+                                                        self.looks[self.look].label
+                                                    ))
+                                                    .size(28),
+                                                )
+                                            } else {
+                                                None
+                                            })
+                                            .push_maybe(if self.backgrounds.len() > 1 {
+                                                Some(
+                                                    text(format!(
+                                                        "[↑]  {}  [↓]",
+                                                        self.backgrounds[self.background].label
+                                                    ))
+                                                    .size(28),
+                                                )
+                                            } else {
+                                                None
+                                            })
+                                            .push(
+                                                text("Press [Space] to start taking pictures!")
+                                                    .size(34)
+                                                    .vertical_alignment(
+                                                        iced::alignment::Vertical::Center,
+                                                    ),
+                                            )
+                                            .spacing(8)
+                                            .align_items(Alignment::Center),
+                                    )
+                                } else {
+                                    None
+                                })
                                 .spacing(16)
                                 .align_items(Alignment::Center),
                             match self.capture_sequence_state {
@@ -431,6 +867,7 @@ impl super::Screenish for CameraScreen {
                     )
                     .push(element_strip_renderer(
                         self.frame_image_handle.clone(),
+                        self.overlay_image_handles.clone(),
                         &self.captured_frames,
                         &self.config.template,
                         if matches!(
@@ -442,6 +879,7 @@ impl super::Screenish for CameraScreen {
                             None
                         },
                         frame_size_animation_value,
+                        self.captured_frames.len(),
                     ))
                     .spacing(20)
                     .align_items(Alignment::Center),
@@ -474,13 +912,58 @@ impl super::Screenish for CameraScreen {
             self.feed
                 .subscription()
                 .map(CameraScreenMessage::CameraFeedMessage),
-            iced::keyboard::on_key_press(|key, _modifiers| match key {
-                iced::keyboard::Key::Named(iced::keyboard::key::Named::Space)
-                | iced::keyboard::Key::Named(iced::keyboard::key::Named::Enter) => {
-                    Some(CameraScreenMessage::CaptureButtonPressed)
-                }
-                _ => None,
-            }),
+            if self.admin {
+                iced::keyboard::on_key_press(|key, _modifiers| match key.as_ref() {
+                    iced::keyboard::Key::Named(iced::keyboard::key::Named::ArrowLeft) => {
+                        Some(CameraScreenMessage::PanPressed(-PAN_STEP, 0.0))
+                    }
+                    iced::keyboard::Key::Named(iced::keyboard::key::Named::ArrowRight) => {
+                        Some(CameraScreenMessage::PanPressed(PAN_STEP, 0.0))
+                    }
+                    iced::keyboard::Key::Named(iced::keyboard::key::Named::ArrowUp) => {
+                        Some(CameraScreenMessage::PanPressed(0.0, -PAN_STEP))
+                    }
+                    iced::keyboard::Key::Named(iced::keyboard::key::Named::ArrowDown) => {
+                        Some(CameraScreenMessage::PanPressed(0.0, PAN_STEP))
+                    }
+                    iced::keyboard::Key::Character("+" | "=") => {
+                        Some(CameraScreenMessage::ZoomPressed(ZOOM_STEP))
+                    }
+                    iced::keyboard::Key::Character("-") => {
+                        Some(CameraScreenMessage::ZoomPressed(1.0 / ZOOM_STEP))
+                    }
+                    iced::keyboard::Key::Character("0") => {
+                        Some(CameraScreenMessage::ZoomResetPressed)
+                    }
+                    iced::keyboard::Key::Named(
+                        iced::keyboard::key::Named::F2 | iced::keyboard::key::Named::Escape,
+                    ) => Some(CameraScreenMessage::AdminModePressed),
+                    _ => None,
+                })
+            } else {
+                iced::keyboard::on_key_press(|key, _modifiers| match key {
+                    iced::keyboard::Key::Named(iced::keyboard::key::Named::F2) => {
+                        Some(CameraScreenMessage::AdminModePressed)
+                    }
+                    iced::keyboard::Key::Named(iced::keyboard::key::Named::Space)
+                    | iced::keyboard::Key::Named(iced::keyboard::key::Named::Enter) => {
+                        Some(CameraScreenMessage::CaptureButtonPressed)
+                    }
+                    iced::keyboard::Key::Named(iced::keyboard::key::Named::ArrowLeft) => {
+                        Some(CameraScreenMessage::PreviousLookPressed)
+                    }
+                    iced::keyboard::Key::Named(iced::keyboard::key::Named::ArrowRight) => {
+                        Some(CameraScreenMessage::NextLookPressed)
+                    }
+                    iced::keyboard::Key::Named(iced::keyboard::key::Named::ArrowUp) => {
+                        Some(CameraScreenMessage::PreviousBackgroundPressed)
+                    }
+                    iced::keyboard::Key::Named(iced::keyboard::key::Named::ArrowDown) => {
+                        Some(CameraScreenMessage::NextBackgroundPressed)
+                    }
+                    _ => None,
+                })
+            },
             if self.counter_timeline.status().is_animating()
                 || self.snap_timeline.status().is_animating()
                 || self.frame_size_timeline.status().is_animating()
@@ -496,6 +979,82 @@ impl super::Screenish for CameraScreen {
     }
 }
 
+impl CameraScreen {
+    /// Render the session's texts and the template guides with them, off the
+    /// UI thread since it's done at the strip's full resolution. The guide
+    /// bases are only made the first time.
+    fn render_decorations(&self) -> iced::Command<super::ScreenMessage> {
+        let config = self.config.clone();
+        let stamp_values = self.stamp_values.clone();
+        let guide_bases = self.guide_bases.clone();
+        iced::Command::perform(
+            async move {
+                tokio::task::spawn_blocking(move || {
+                    let text_image = text_image(&config, &stamp_values);
+                    let guide_bases =
+                        guide_bases.or_else(|| template_guide_bases(&config).map(Arc::new));
+                    let guides = match &guide_bases {
+                        Some(bases) => guide_handles(&config, bases, text_image.as_ref()),
+                        None => vec![],
+                    };
+                    (
+                        overlay_image_handles(&config, text_image.as_ref()),
+                        guides,
+                        guide_bases,
+                    )
+                })
+                .await
+                .unwrap()
+            },
+            |(overlay_image_handles, guides, guide_bases)| {
+                CameraScreenMessage::DecorationsRendered(overlay_image_handles, guides, guide_bases)
+                    .into()
+            },
+        )
+    }
+
+    /// The live preview, with the template guide for the next frame over it.
+    fn preview(&self) -> Element<'_, CameraScreenMessage> {
+        let feed = self.feed.view().width(Length::Fill).height(Length::Fill);
+        match self.guides.get(self.captured_frames.len()) {
+            // both are the shape of the frame, so they fit the same space the
+            // same way
+            Some(guide) => floating_element(
+                feed,
+                iced::widget::image(guide.clone())
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .offset(0.0)
+            .into(),
+            None => feed.into(),
+        }
+    }
+
+    /// The chroma key and the background the guest picked, if there is one.
+    fn chroma_key(&self) -> Option<(ChromaKeyer, Arc<RgbaImage>)> {
+        self.chroma_keyer
+            .map(|keyer| (keyer, self.backgrounds[self.background].image.clone()))
+    }
+
+    /// Zoom the preview and the photos in by `zoom` around `centre`, keeping
+    /// the zoomed-in part inside the camera's frame.
+    fn set_digital_zoom(&mut self, zoom: f32, centre: (f32, f32)) {
+        let zoom = zoom.clamp(1.0, MAX_ZOOM);
+        let margin = 0.5 / zoom;
+        let centre = (
+            centre.0.clamp(margin, 1.0 - margin),
+            centre.1.clamp(margin, 1.0 - margin),
+        );
+        self.config.digital_zoom = DigitalZoom {
+            zoom,
+            x: centre.0,
+            y: centre.1,
+        };
+        self.feed.set_digital_zoom(zoom, centre);
+    }
+}
+
 impl Into<super::Screen> for CameraScreen {
     fn into(self) -> super::Screen {
         super::Screen::CameraScreen(self)
@@ -1,18 +1,38 @@
-mod border_radius;
-
 use iced::widget::image::Handle;
 use iced::{Command, Subscription};
-use image::RgbaImage;
+use image::{imageops, RgbaImage};
 use nokhwa::pixel_format::RgbAFormat;
 use nokhwa::Camera;
+use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::CameraRotation;
+use crate::utils::{
+    border_radius::BorderRadius, chroma_key::ChromaKeyer, color_lut::Lut3d, crop_focus::CropFocus,
+    preview_renderer::PreviewRenderer,
+};
 
-use self::border_radius::BorderRadius;
+/// Used to pace the preview when the camera doesn't report a frame rate.
+const FALLBACK_FRAME_RATE: u32 = 30;
+/// How often the preview crop follows the guests when it depends on what's in
+/// the frame. Finding faces is too slow to do for every frame.
+const FOCUS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Where the preview crop is centred and when that was last worked out.
+#[derive(Debug, Clone, Copy)]
+struct PreviewFocus {
+    focus: (f32, f32),
+    updated_at: Option<Instant>,
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum CameraMessage {
+    Start,
     CaptureFrame,
-    NewFrame(Handle),
+    /// The next preview frame, or `None` if the camera didn't give one.
+    NewFrame(Option<Handle>),
+    DisplaySizeChanged(u32, u32),
 }
 
 /// Camera feed.
@@ -20,19 +40,84 @@ pub enum CameraMessage {
 pub struct CameraFeed {
     camera: Arc<Mutex<nokhwa::Camera>>,
     current_frame: Arc<Mutex<Option<Handle>>>,
-    border_radius: BorderRadius,
-    mirror: bool,
-    aspect_ratio: Option<f32>,
+    /// The most recent frames from the camera and when they were taken,
+    /// oldest first, so a photo can be picked from the instant of the shutter.
+    /// The oldest one's buffer is reused for the next frame.
+    recent_frames: Arc<Mutex<VecDeque<(Instant, RgbaImage)>>>,
+    recent_frames_len: usize,
+    renderer: Arc<PreviewRenderer>,
+    crop_focus: Arc<CropFocus>,
+    preview_focus: Arc<Mutex<PreviewFocus>>,
+    rotation: CameraRotation,
+    frame_interval: Duration,
+    capture_started: Instant,
 }
 
-fn frame_and_decode(camera: &mut Camera) -> RgbaImage {
-    // TODO: it might be more performant to pre-allocate the buffer and use
-    //       write_frame_to_buffer instead
-    camera
+/// Decode the next frame from the camera into `buffer`, which is resized to
+/// fit if it has to be, and turn it the right way up. The frame comes back
+/// with when the camera handed it over.
+fn frame_and_decode(
+    camera: &mut Camera,
+    mut buffer: Vec<u8>,
+    rotation: CameraRotation,
+) -> Result<(Instant, RgbaImage), String> {
+    let raw = camera
         .frame()
-        .expect("failed to capture a camera frame")
-        .decode_image::<RgbAFormat>()
-        .expect("failed to decode the camera frame")
+        .map_err(|e| format!("failed to capture a camera frame: {}", e))?;
+    // before decoding, which takes a while for big frames
+    let taken_at = Instant::now();
+    let resolution = raw.resolution();
+    let (width, height) = (resolution.width(), resolution.height());
+    buffer.resize(width as usize * height as usize * 4, 0);
+    raw.decode_image_to_buffer::<RgbAFormat>(&mut buffer)
+        .map_err(|e| format!("failed to decode a camera frame: {}", e))?;
+    let mut frame =
+        RgbaImage::from_raw(width, height, buffer).expect("frame buffer is the size of the frame");
+    // quarter turns swap the width and height, so they can't reuse the buffer
+    let frame = match rotation {
+        CameraRotation::None => frame,
+        CameraRotation::Clockwise90 => imageops::rotate90(&frame),
+        CameraRotation::Clockwise180 => {
+            imageops::rotate180_in_place(&mut frame);
+            frame
+        }
+        CameraRotation::Clockwise270 => imageops::rotate270(&frame),
+    };
+    Ok((taken_at, frame))
+}
+
+/// A buffer to decode the next frame into, taken from the oldest recent frame
+/// if there's no room to keep another one.
+fn spare_buffer(
+    recent_frames: &Mutex<VecDeque<(Instant, RgbaImage)>>,
+    recent_frames_len: usize,
+) -> Vec<u8> {
+    let mut recent_frames = recent_frames.lock().expect("failed to lock recent frames");
+    if recent_frames.len() >= recent_frames_len.max(1) {
+        recent_frames
+            .pop_front()
+            .map(|(_, frame)| frame.into_raw())
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    }
+}
+fn remember_frame(
+    recent_frames: &Mutex<VecDeque<(Instant, RgbaImage)>>,
+    recent_frames_len: usize,
+    taken_at: Instant,
+    frame: RgbaImage,
+) {
+    let mut recent_frames = recent_frames.lock().expect("failed to lock recent frames");
+    while recent_frames.len() >= recent_frames_len.max(1) {
+        recent_frames.pop_front();
+    }
+    recent_frames.push_back((taken_at, frame));
+}
+
+fn time_between(a: Instant, b: Instant) -> Duration {
+    a.saturating_duration_since(b)
+        .max(b.saturating_duration_since(a))
 }
 
 impl CameraFeed {
@@ -41,103 +126,230 @@ impl CameraFeed {
         border_radius: BorderRadius,
         mirror: bool,
         aspect_ratio: Option<f32>,
+        recent_frames_len: usize,
+        crop_focus: CropFocus,
     ) -> (Self, Option<CameraMessage>) {
+        let frame_rate = match camera.frame_rate() {
+            0 => FALLBACK_FRAME_RATE,
+            frame_rate => frame_rate,
+        };
         (
             CameraFeed {
                 camera: Arc::new(Mutex::new(camera)),
                 current_frame: Arc::new(Mutex::new(None)),
-                border_radius,
-                mirror,
-                aspect_ratio,
+                recent_frames: Arc::new(Mutex::new(VecDeque::with_capacity(recent_frames_len))),
+                recent_frames_len,
+                renderer: Arc::new(PreviewRenderer::new(border_radius, mirror, aspect_ratio)),
+                crop_focus: Arc::new(crop_focus),
+                preview_focus: Arc::new(Mutex::new(PreviewFocus {
+                    focus: (0.5, 0.5),
+                    updated_at: None,
+                })),
+                rotation: CameraRotation::None,
+                frame_interval: Duration::from_secs(1) / frame_rate,
+                capture_started: Instant::now(),
             },
-            Some(CameraMessage::CaptureFrame),
+            Some(CameraMessage::Start),
         )
     }
 
-    /// Take an image outside of the normal video capture cycle
-    pub fn frame(&mut self) -> RgbaImage {
-        frame_and_decode(&mut self.camera.lock().expect("failed to lock camera mutex"))
+    /// The `count` recent frames taken nearest to `instant`, nearest first.
+    ///
+    /// Blocks until `instant` if it's in the future, and takes a frame outside
+    /// of the normal video capture cycle if the preview hasn't got one since,
+    /// which fails if the camera doesn't give one.
+    pub fn frames_near(
+        &mut self,
+        instant: Instant,
+        count: usize,
+    ) -> Result<Vec<RgbaImage>, String> {
+        let now = Instant::now();
+        if instant > now {
+            std::thread::sleep(instant - now);
+        }
+        let caught_up = self
+            .recent_frames
+            .lock()
+            .expect("failed to lock recent frames")
+            .back()
+            .is_some_and(|(taken_at, _)| *taken_at >= instant);
+        if !caught_up {
+            let (taken_at, frame) = frame_and_decode(
+                &mut self.camera.lock().expect("failed to lock camera mutex"),
+                Vec::new(),
+                self.rotation,
+            )?;
+            remember_frame(&self.recent_frames, self.recent_frames_len, taken_at, frame);
+        }
+
+        let recent_frames = self
+            .recent_frames
+            .lock()
+            .expect("failed to lock recent frames");
+        let mut nearest: Vec<_> = recent_frames.iter().collect();
+        nearest.sort_by_key(|(taken_at, _)| time_between(*taken_at, instant));
+        Ok(nearest
+            .into_iter()
+            .take(count.max(1))
+            .map(|(_, frame)| frame.clone())
+            .collect())
     }
 
     pub fn update(&mut self, message: CameraMessage) -> Command<CameraMessage> {
         match message {
+            CameraMessage::Start => Command::batch([
+                iced::window::fetch_size(iced::window::Id::MAIN, |size| {
+                    CameraMessage::DisplaySizeChanged(size.width as u32, size.height as u32)
+                }),
+                Command::perform(async {}, |_| CameraMessage::CaptureFrame),
+            ]),
             CameraMessage::CaptureFrame => {
+                self.capture_started = Instant::now();
                 let cloned_camera = self.camera.clone();
-                let border_radius = self.border_radius;
-                let aspect_ratio = self.aspect_ratio;
-                let mirror = self.mirror;
+                let recent_frames = self.recent_frames.clone();
+                let recent_frames_len = self.recent_frames_len;
+                let renderer = self.renderer.clone();
+                let crop_focus = self.crop_focus.clone();
+                let preview_focus = self.preview_focus.clone();
+                let rotation = self.rotation;
                 Command::perform(
                     async move {
                         tokio::task::spawn_blocking(move || {
-                            let mut frame = frame_and_decode(
+                            let buffer = spare_buffer(&recent_frames, recent_frames_len);
+                            let (taken_at, frame) = match frame_and_decode(
                                 &mut cloned_camera.lock().expect("failed to lock camera mutex"),
-                            );
-
-                            // crop the frame to meet the aspect ratio
-                            let mut frame = if let Some(aspect_ratio) = aspect_ratio {
-                                let frame_aspect_ratio =
-                                    frame.width() as f32 / frame.height() as f32;
-                                let new_width;
-                                let new_height;
-                                let left_offset;
-                                let top_offset;
-                                if aspect_ratio < frame_aspect_ratio {
-                                    // trim off left and right
-                                    new_height = frame.height();
-                                    new_width = (frame.height() as f32 * aspect_ratio) as u32;
-                                    left_offset = (frame.width() - new_width) / 2;
-                                    top_offset = 0;
-                                } else if aspect_ratio > frame_aspect_ratio {
-                                    // trim off top and bottom
-                                    new_width = frame.width();
-                                    new_height = (frame.width() as f32 / aspect_ratio) as u32;
-                                    top_offset = (frame.height() - new_height) / 2;
-                                    left_offset = 0;
-                                } else {
-                                    // perfect aspect ratio!
-                                    new_width = frame.width();
-                                    new_height = frame.height();
-                                    top_offset = 0;
-                                    left_offset = 0;
+                                buffer,
+                                rotation,
+                            ) {
+                                Ok(frame) => frame,
+                                Err(e) => {
+                                    // the next one will probably come through
+                                    eprintln!("warning: {}", e);
+                                    return None;
                                 }
-                                image::imageops::crop(
-                                    &mut frame,
-                                    left_offset,
-                                    top_offset,
-                                    new_width,
-                                    new_height,
-                                )
-                                .to_image() // this might be pricy...
-                            } else {
-                                frame
                             };
 
-                            // mirror the frame
-                            if mirror {
-                                image::imageops::flip_horizontal_in_place(&mut frame);
-                            }
+                            // follow the guests if the crop depends on them
+                            let focus = {
+                                let mut preview_focus =
+                                    preview_focus.lock().expect("failed to lock preview focus");
+                                let due = match preview_focus.updated_at {
+                                    Some(updated_at) => {
+                                        crop_focus.is_dynamic()
+                                            && updated_at.elapsed() >= FOCUS_INTERVAL
+                                    }
+                                    None => true,
+                                };
+                                if due {
+                                    // on the zoomed-in part, the same as
+                                    // the captured photos
+                                    let (left, top, width, height) =
+                                        renderer.zoom_rect(frame.width(), frame.height());
+                                    let (x, y) = if (width, height) == frame.dimensions() {
+                                        crop_focus.focus(&frame)
+                                    } else {
+                                        crop_focus.focus(
+                                            &imageops::crop_imm(&frame, left, top, width, height)
+                                                .to_image(),
+                                        )
+                                    };
+                                    preview_focus.focus = match preview_focus.updated_at {
+                                        // ease towards the new focus so the crop
+                                        // doesn't jump around
+                                        Some(_) => (
+                                            (preview_focus.focus.0 + x) / 2.0,
+                                            (preview_focus.focus.1 + y) / 2.0,
+                                        ),
+                                        None => (x, y),
+                                    };
+                                    preview_focus.updated_at = Some(taken_at);
+                                }
+                                preview_focus.focus
+                            };
 
-                            // apply border radius
-                            border_radius::round(&mut frame, &border_radius);
+                            // crop, mirror, scale down and round off the preview
+                            let preview = renderer.render(&frame, focus);
+                            remember_frame(&recent_frames, recent_frames_len, taken_at, frame);
 
                             // output a handle
-                            Handle::from_pixels(frame.width(), frame.height(), frame.into_raw())
+                            Some(Handle::from_pixels(
+                                preview.width(),
+                                preview.height(),
+                                preview.into_raw(),
+                            ))
                         })
                         .await
-                        .unwrap()
+                        .unwrap_or(None)
                     },
                     CameraMessage::NewFrame,
                 )
             }
             CameraMessage::NewFrame(data) => {
-                *self.current_frame.lock().expect("failed to lock frame") = Some(data);
-                Command::perform(async {}, |_| CameraMessage::CaptureFrame)
+                if let Some(data) = data {
+                    *self.current_frame.lock().expect("failed to lock frame") = Some(data);
+                }
+                // there's no point asking for frames faster than the camera
+                // makes them
+                let wait = self
+                    .frame_interval
+                    .saturating_sub(self.capture_started.elapsed());
+                Command::perform(tokio::time::sleep(wait), |_| CameraMessage::CaptureFrame)
+            }
+            CameraMessage::DisplaySizeChanged(width, height) => {
+                Arc::make_mut(&mut self.renderer).set_max_size(width, height);
+                Command::none()
             }
         }
     }
 
     pub fn subscription(&self) -> Subscription<CameraMessage> {
-        Subscription::none()
+        iced::event::listen_with(|event, _status| match event {
+            iced::Event::Window(
+                iced::window::Id::MAIN,
+                iced::window::Event::Resized { width, height },
+            ) => Some(CameraMessage::DisplaySizeChanged(width, height)),
+            _ => None,
+        })
+    }
+
+    /// Filter the preview through `lut`, or stop filtering it.
+    pub fn set_look(&mut self, lut: Option<Arc<Lut3d>>) {
+        Arc::make_mut(&mut self.renderer).set_lut(lut);
+    }
+
+    /// Replace the backdrop in the preview with `background` using `keyer`,
+    /// or stop replacing it.
+    pub fn set_chroma_key(&mut self, chroma_key: Option<(ChromaKeyer, Arc<RgbaImage>)>) {
+        Arc::make_mut(&mut self.renderer).set_chroma_key(chroma_key);
+    }
+
+    /// How the preview decides where to crop, so photos can be cropped the
+    /// same way.
+    pub fn crop_focus(&self) -> Arc<CropFocus> {
+        self.crop_focus.clone()
+    }
+
+    /// Crop the preview to `aspect_ratio`, or stop cropping it.
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: Option<f32>) {
+        Arc::make_mut(&mut self.renderer).set_aspect_ratio(aspect_ratio);
+    }
+
+    /// Turn the camera's frames clockwise by `rotation`, before anything else
+    /// is done to them.
+    pub fn set_rotation(&mut self, rotation: CameraRotation) {
+        self.rotation = rotation;
+    }
+
+    /// Zoom the preview in by `zoom` around `centre`, given as fractions of
+    /// the frame's width and height.
+    pub fn set_digital_zoom(&mut self, zoom: f32, centre: (f32, f32)) {
+        Arc::make_mut(&mut self.renderer).set_zoom(zoom, centre);
+    }
+
+    /// The camera, to change its controls. The preview waits while it's
+    /// locked, so it shouldn't be held for long.
+    pub fn camera(&self) -> Arc<Mutex<nokhwa::Camera>> {
+        self.camera.clone()
     }
 
     /// Get the image handle of the current frame.
@@ -1,26 +1,27 @@
+use std::sync::Arc;
+
 use iced::{
+    theme,
     widget::{container, image::Handle, Responsive, Space},
     Color, Length,
 };
 use iced_aw::floating_element;
-use image::Rgba;
+use image::RgbaImage;
 
-use crate::config::Template;
+use crate::{config::Template, utils::container_styles::NextSlotContainerStyle};
 
-/// # Panics
-///
-/// Panics if the captured_frames count exceeds the amount in the template.
+/// Captured frames that don't fit in the template are left out. The frame at
+/// `next_slot`, if the template has one, is outlined above everything else.
 pub(super) fn element_strip_renderer<'a>(
     handle: iced::widget::image::Handle,
-    captured_frames: &'a Vec<(image::ImageBuffer<Rgba<u8>, Vec<u8>>, Handle)>,
+    overlay_handles: Vec<iced::widget::image::Handle>,
+    captured_frames: &'a [(Arc<RgbaImage>, Handle)],
     template: &'a Template,
     snap_animation_value: Option<f32>,
     frame_size_animation_value: f32,
+    next_slot: usize,
 ) -> iced::Element<'a, super::CameraScreenMessage> {
-    if captured_frames.len() > template.frames.len() {
-        panic!("captured_frames count exceeds number of frames in template");
-    }
-    floating_element(
+    let strip = floating_element(
         iced::widget::Image::new(handle),
         Responsive::new(move |size| {
             let mut element: iced::Element<'a, super::CameraScreenMessage> =
@@ -30,8 +31,9 @@ pub(super) fn element_strip_renderer<'a>(
                     .into();
             let x_factor = size.width / template.width;
             let y_factor = size.height / template.height;
-            for (i, (_, frame_handle)) in captured_frames.iter().enumerate() {
-                let template_frame = &template.frames[i];
+            for (i, ((_, frame_handle), template_frame)) in
+                captured_frames.iter().zip(&template.frames).enumerate()
+            {
                 let is_last = i == captured_frames.len() - 1;
                 let animation_factor = if is_last {
                     frame_size_animation_value
@@ -73,6 +75,49 @@ pub(super) fn element_strip_renderer<'a>(
             element
         }),
     )
+    .offset(0.0);
+    // overlays go on top of the photos so they can cover their edges
+    let strip = overlay_handles
+        .into_iter()
+        .fold(strip.into(), |element, overlay_handle| {
+            floating_element(
+                element,
+                iced::widget::Image::new(overlay_handle)
+                    .content_fit(iced::ContentFit::Fill)
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            )
+            .offset(0.0)
+            .into()
+        });
+    let Some(next_frame) = template.frames.get(next_slot) else {
+        return strip;
+    };
+    // the outline goes over the overlays, which could otherwise hide it
+    floating_element(
+        strip,
+        Responsive::new(move |size| {
+            let x_factor = size.width / template.width;
+            let y_factor = size.height / template.height;
+            floating_element(
+                container(Space::new(0, 0))
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+                container(Space::new(0, 0))
+                    .width(next_frame.width * x_factor)
+                    .height(next_frame.height * y_factor)
+                    .style(theme::Container::Custom(Box::new(
+                        NextSlotContainerStyle {},
+                    ))),
+            )
+            .anchor(floating_element::Anchor::NorthWest)
+            .offset(floating_element::Offset {
+                x: next_frame.x * x_factor,
+                y: next_frame.y * y_factor,
+            })
+            .into()
+        }),
+    )
     .offset(0.0)
     .into()
 }
@@ -0,0 +1,119 @@
+use iced::widget::image::Handle;
+use image::{imageops, Rgba, RgbaImage};
+
+use crate::{
+    config::{Frame, Template, TemplateGuide},
+    screens::generation_screen::frame_shape::{apply_frame_shape, load_frame_masks, FrameMasks},
+};
+
+/// The longest side of a guide. It's stretched over the preview, which is
+/// scaled down anyway, so there's no point making it any sharper.
+const GUIDE_MAX_SIZE: u32 = 720;
+/// What the parts of a frame its shape cuts off are shaded with, before the
+/// guide's opacity.
+const SHADE: [u8; 3] = [0, 0, 0];
+
+/// The part of `decoration`, which covers the whole template, that sits on
+/// `frame`, scaled to `width` by `height`.
+fn decoration_over(
+    decoration: &RgbaImage,
+    template: &Template,
+    frame: &Frame,
+    width: u32,
+    height: u32,
+) -> RgbaImage {
+    let x_scale = decoration.width() as f32 / template.width;
+    let y_scale = decoration.height() as f32 / template.height;
+    let left = ((frame.x * x_scale).round() as u32).min(decoration.width() - 1);
+    let top = ((frame.y * y_scale).round() as u32).min(decoration.height() - 1);
+    let crop_width = ((frame.width * x_scale).round() as u32).clamp(1, decoration.width() - left);
+    let crop_height = ((frame.height * y_scale).round() as u32).clamp(1, decoration.height() - top);
+    let cropped = imageops::crop_imm(decoration, left, top, crop_width, crop_height);
+    imageops::resize(&*cropped, width, height, imageops::FilterType::Triangle)
+}
+
+/// What the shape of `frame` cuts off shaded, with the part of `overlay`
+/// that sits on it. This is the part of its guide that stays the same from
+/// one session to the next.
+fn guide_base(
+    template: &Template,
+    frame: &Frame,
+    masks: &FrameMasks,
+    overlay: Option<&RgbaImage>,
+) -> RgbaImage {
+    let scale =
+        (GUIDE_MAX_SIZE as f32 / frame.width.max(frame.height)).min(template.pixels_per_unit());
+    let width = ((frame.width * scale).round() as u32).max(1);
+    let height = ((frame.height * scale).round() as u32).max(1);
+    let mut base = RgbaImage::new(width, height);
+    if let Some(shape) = &frame.shape {
+        // cut a solid frame to the shape, and shade whatever didn't make it
+        let mut kept = RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255]));
+        match apply_frame_shape(&mut kept, shape, scale, masks) {
+            Ok(()) => {
+                for (pixel, kept) in base.pixels_mut().zip(kept.pixels()) {
+                    *pixel = Rgba([SHADE[0], SHADE[1], SHADE[2], 255 - kept.0[3]]);
+                }
+            }
+            Err(e) => eprintln!(
+                "warning: the template guide won't show the frame's shape: {}",
+                e
+            ),
+        }
+    }
+    if let Some(overlay) = overlay {
+        let overlay = decoration_over(overlay, template, frame, width, height);
+        imageops::overlay(&mut base, &overlay, 0, 0);
+    }
+    base
+}
+
+/// The bases of the guides for each frame of `template`, worked out once
+/// since they're slow to make at the template's resolution. `overlay` is
+/// the template overlay, if it has one.
+pub(super) fn guide_bases(template: &Template, overlay: Option<&RgbaImage>) -> Vec<RgbaImage> {
+    // a frame whose mask won't open warns when its shape is cut
+    let masks = load_frame_masks(template).unwrap_or_else(|e| {
+        eprintln!("warning: {}", e);
+        FrameMasks::new()
+    });
+    template
+        .frames
+        .iter()
+        .map(|frame| guide_base(template, frame, &masks, overlay))
+        .collect()
+}
+
+/// A guide to show over the preview for each frame of `template`, made from
+/// its `bases` with the part of `text_image`, which covers the whole template,
+/// that sits on each frame. `mirrored` is set when the preview is mirrored but
+/// the photos aren't, or the other way round.
+pub(super) fn template_guides(
+    template: &Template,
+    bases: &[RgbaImage],
+    text_image: Option<&RgbaImage>,
+    settings: &TemplateGuide,
+    mirrored: bool,
+) -> Vec<Handle> {
+    let opacity = settings.opacity.clamp(0.0, 1.0);
+    template
+        .frames
+        .iter()
+        .zip(bases)
+        .map(|(frame, base)| {
+            let mut guide = base.clone();
+            if let Some(text_image) = text_image {
+                let texts =
+                    decoration_over(text_image, template, frame, guide.width(), guide.height());
+                imageops::overlay(&mut guide, &texts, 0, 0);
+            }
+            if mirrored {
+                imageops::flip_horizontal_in_place(&mut guide);
+            }
+            for pixel in guide.pixels_mut() {
+                pixel.0[3] = (pixel.0[3] as f32 * opacity).round() as u8;
+            }
+            Handle::from_pixels(guide.width(), guide.height(), guide.into_raw())
+        })
+        .collect()
+}
@@ -1,8 +1,8 @@
 use std::fmt::Display;
 
 use iced::{
-    alignment,
-    widget::{button, combo_box, container, text, Column, Space},
+    alignment, theme,
+    widget::{button, combo_box, container, text, Column, Row, Space},
     Command, Element, Length,
 };
 use nokhwa::utils::CameraInfo;
@@ -37,12 +37,15 @@ pub enum ConfigScreenMessage {
     CameraSelected(CameraWrapper),
     OptionHovered(CameraWrapper),
     Closed,
+    CameraSettingsPressed,
     Next,
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct ConfigScreenFlags {
     pub config: Config,
+    /// The camera to start with selected.
+    pub camera: Option<nokhwa::utils::CameraIndex>,
 }
 
 impl Into<super::ScreenMessage> for ConfigScreenMessage {
@@ -60,10 +63,19 @@ impl super::Screenish for ConfigScreen {
             .into_iter()
             .map(|info| CameraWrapper::from(info))
             .collect::<Vec<CameraWrapper>>();
+        let selected_camera = flags.camera.and_then(|index| {
+            cameras
+                .iter()
+                .find(|camera| camera.0.index() == &index)
+                .cloned()
+        });
         (
             ConfigScreen {
-                selected_camera: None,
-                text: String::new(),
+                text: selected_camera
+                    .as_ref()
+                    .map(|camera| camera.to_string())
+                    .unwrap_or_default(),
+                selected_camera,
                 cameras: combo_box::State::new(cameras),
                 config: flags.config,
             },
@@ -89,6 +101,23 @@ impl super::Screenish for ConfigScreen {
                     .unwrap_or_default();
                 Command::none()
             }
+            ConfigScreenMessage::CameraSettingsPressed => {
+                let flags = super::camera_controls_screen::CameraControlsScreenFlags {
+                    index: self
+                        .selected_camera
+                        .as_ref()
+                        .expect("selected camera is None")
+                        .0
+                        .index()
+                        .clone(),
+                    config: self.config.clone(),
+                };
+                Command::perform(async {}, |_| {
+                    super::ScreenMessage::TransitionToScreen(
+                        super::ScreenFlags::CameraControlsScreenFlags(flags),
+                    )
+                })
+            }
             ConfigScreenMessage::Next => {
                 let flags = super::camera_screen::CameraScreenFlags {
                     index: self
@@ -127,17 +156,35 @@ impl super::Screenish for ConfigScreen {
                 )
                 .push(Space::with_height(12))
                 .push(
-                    button(
-                        text("Start photo booth")
-                            .size(18)
-                            .horizontal_alignment(alignment::Horizontal::Center),
-                    )
-                    .on_press_maybe(
-                        self.selected_camera
-                            .as_ref()
-                            .and(Some(ConfigScreenMessage::Next)),
-                    )
-                    .padding(6),
+                    Row::new()
+                        .push(
+                            button(
+                                text("Camera settings")
+                                    .size(18)
+                                    .horizontal_alignment(alignment::Horizontal::Center),
+                            )
+                            .on_press_maybe(
+                                self.selected_camera
+                                    .as_ref()
+                                    .and(Some(ConfigScreenMessage::CameraSettingsPressed)),
+                            )
+                            .style(theme::Button::Secondary)
+                            .padding(6),
+                        )
+                        .push(
+                            button(
+                                text("Start photo booth")
+                                    .size(18)
+                                    .horizontal_alignment(alignment::Horizontal::Center),
+                            )
+                            .on_press_maybe(
+                                self.selected_camera
+                                    .as_ref()
+                                    .and(Some(ConfigScreenMessage::Next)),
+                            )
+                            .padding(6),
+                        )
+                        .spacing(12),
                 ),
         )
         .width(Length::Fill)
@@ -0,0 +1,343 @@
+use std::{sync::Arc, time::Duration};
+
+use iced::{
+    theme,
+    widget::{button, container, image::Handle, text, Column, Image, Row},
+    Element, Length,
+};
+use image::RgbaImage;
+
+use crate::{
+    config::{Config, DeliveryMethod, DeliveryOption},
+    utils::{
+        container_styles::RoundedBoxContainerStyle, image_encoding::Attachment,
+        image_metadata::ImageMetadata,
+    },
+};
+
+/// The delivery option the guest picked and how far along it we are. This is
+/// passed from screen to screen so combinations like "print and email" can be
+/// run one method at a time.
+#[derive(Debug, Clone)]
+pub(crate) struct DeliveryPlan {
+    pub option: DeliveryOption,
+    pub step: usize,
+    /// What the guest should know about methods that have run, shown once
+    /// they all have.
+    pub messages: Vec<String>,
+    /// What went wrong with methods that failed, shown once the others have
+    /// run.
+    pub failures: Vec<String>,
+}
+
+impl DeliveryPlan {
+    pub fn new(option: DeliveryOption) -> Self {
+        DeliveryPlan {
+            option,
+            step: 0,
+            messages: vec![],
+            failures: vec![],
+        }
+    }
+
+    /// The plan with the current method marked as done, with `message` to
+    /// show the guest at the end.
+    pub fn done(mut self, message: &str) -> Self {
+        self.messages.push(message.to_string());
+        self.step += 1;
+        self
+    }
+
+    /// The plan with the current method marked as failed because of
+    /// `failure`, moving on to the next one.
+    pub fn failed(mut self, failure: String) -> Self {
+        self.failures.push(failure);
+        self.step += 1;
+        self
+    }
+
+    /// Whether there's a method left to run after the current one.
+    pub fn has_next(&self) -> bool {
+        self.step + 1 < self.option.methods.len()
+    }
+
+    pub fn current(&self) -> Option<DeliveryMethod> {
+        self.option.methods.get(self.step).copied()
+    }
+
+    /// Whether any method other than the current one is part of the plan.
+    pub fn is_combination(&self) -> bool {
+        self.option.methods.len() > 1
+    }
+}
+
+/// Get the flags for the screen that runs the current step of `plan`. Once
+/// every method has run, an alert showing what each of them did is returned
+/// instead.
+pub(crate) fn continue_delivery(
+    config: Config,
+    index: nokhwa::utils::CameraIndex,
+    preview_handle: Handle,
+    printable_image: Arc<RgbaImage>,
+    metadata: ImageMetadata,
+    attachments: Vec<Attachment>,
+    plan: DeliveryPlan,
+) -> super::ScreenFlags {
+    match plan.current() {
+        Some(DeliveryMethod::Email) => {
+            super::ScreenFlags::EmailScreenFlags(super::email_screen::EmailScreenFlags {
+                config,
+                index,
+
+                preview_handle,
+                printable_image,
+                metadata,
+                attachments,
+                delivery_plan: plan,
+            })
+        }
+        Some(DeliveryMethod::Print) => {
+            super::ScreenFlags::PrintingScreenFlags(super::printing_screen::PrintingScreenFlags {
+                config,
+                index,
+
+                preview_handle,
+                printable_image,
+                metadata,
+                attachments,
+                delivery_plan: plan,
+            })
+        }
+        Some(DeliveryMethod::Qr) => {
+            super::ScreenFlags::QrScreenFlags(super::qr_screen::QrScreenFlags {
+                config,
+                index,
+
+                preview_handle,
+                printable_image,
+                metadata,
+                attachments,
+                delivery_plan: plan,
+            })
+        }
+        Some(DeliveryMethod::Discard) => {
+            super::ScreenFlags::AlertScreenFlags(super::alert_screen::AlertScreenFlags {
+                config,
+                index,
+
+                alert_title: "Successfully deleted".into(),
+                alert_content:
+                    "Your photos were deleted without being saved and were not uploaded to our server"
+                        .into(),
+                timeout: Duration::from_millis(4000),
+            })
+        }
+        None if plan.failures.is_empty() => {
+            super::ScreenFlags::AlertScreenFlags(super::alert_screen::AlertScreenFlags {
+                config,
+                index,
+
+                alert_title: "All done!".into(),
+                alert_content: plan.messages.join(" "),
+                timeout: Duration::from_millis(4000),
+            })
+        }
+        None => super::ScreenFlags::AlertScreenFlags(super::alert_screen::AlertScreenFlags {
+            config,
+            index,
+
+            alert_title: "Nearly all done".into(),
+            alert_content: plan
+                .messages
+                .iter()
+                .chain(&plan.failures)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(" "),
+            // there's more to read
+            timeout: Duration::from_millis(8000),
+        }),
+    }
+}
+
+/// Get the flags for the screen shown after the strip has been generated. If
+/// there's only one delivery option, the choice screen is skipped.
+pub(crate) fn delivery_flags(
+    config: Config,
+    index: nokhwa::utils::CameraIndex,
+    preview_handle: Handle,
+    printable_image: Arc<RgbaImage>,
+    metadata: ImageMetadata,
+    attachments: Vec<Attachment>,
+) -> super::ScreenFlags {
+    let only_option = {
+        let mut options = config.enabled_delivery_options();
+        match (options.next(), options.next()) {
+            (Some(option), None) => Some(option.clone()),
+            _ => None,
+        }
+    };
+    match only_option {
+        Some(option) => continue_delivery(
+            config,
+            index,
+            preview_handle,
+            printable_image,
+            metadata,
+            attachments,
+            DeliveryPlan::new(option),
+        ),
+        None => super::ScreenFlags::DeliveryScreenFlags(DeliveryScreenFlags {
+            config,
+            index,
+
+            preview_handle,
+            printable_image,
+            metadata,
+            attachments,
+        }),
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct DeliveryScreen {
+    config: Config,
+    index: nokhwa::utils::CameraIndex,
+
+    preview_handle: Handle,
+    printable_image: Option<Arc<RgbaImage>>,
+    metadata: ImageMetadata,
+    attachments: Vec<Attachment>,
+}
+
+#[derive(Debug, Clone)]
+pub enum DeliveryScreenMessage {
+    OptionSelected(usize),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct DeliveryScreenFlags {
+    pub config: Config,
+    pub index: nokhwa::utils::CameraIndex,
+
+    pub preview_handle: Handle,
+    pub printable_image: Arc<RgbaImage>,
+    pub metadata: ImageMetadata,
+    pub attachments: Vec<Attachment>,
+}
+
+impl Into<super::ScreenMessage> for DeliveryScreenMessage {
+    fn into(self) -> super::ScreenMessage {
+        super::ScreenMessage::DeliveryScreenMessage(self)
+    }
+}
+
+impl super::Screenish for DeliveryScreen {
+    type Message = DeliveryScreenMessage;
+    type Flags = DeliveryScreenFlags;
+    fn new(flags: DeliveryScreenFlags) -> (Self, Option<DeliveryScreenMessage>) {
+        (
+            DeliveryScreen {
+                config: flags.config,
+                index: flags.index,
+
+                preview_handle: flags.preview_handle,
+                printable_image: Some(flags.printable_image),
+                metadata: flags.metadata,
+                attachments: flags.attachments,
+            },
+            None,
+        )
+    }
+
+    fn update(&mut self, message: DeliveryScreenMessage) -> iced::Command<super::ScreenMessage> {
+        match message {
+            DeliveryScreenMessage::OptionSelected(i) => {
+                let Some(option) = self.config.enabled_delivery_options().nth(i).cloned() else {
+                    return iced::Command::none();
+                };
+                // taking the image also guards against a double key press
+                let Some(printable_image) = self.printable_image.take() else {
+                    return iced::Command::none();
+                };
+                let flags = continue_delivery(
+                    self.config.clone(),
+                    self.index.clone(),
+                    self.preview_handle.clone(),
+                    printable_image,
+                    self.metadata.clone(),
+                    self.attachments.clone(),
+                    DeliveryPlan::new(option),
+                );
+                iced::Command::perform(async {}, |_| {
+                    super::ScreenMessage::TransitionToScreen(flags)
+                })
+            }
+        }
+    }
+
+    fn view(&self) -> Element<DeliveryScreenMessage> {
+        let options = self.config.enabled_delivery_options().enumerate().fold(
+            Column::new().spacing(10),
+            |column, (i, option)| {
+                column.push(
+                    button(text(format!("[{}] {}", i + 1, option.label)).size(28))
+                        .style(if option.methods.contains(&DeliveryMethod::Discard) {
+                            theme::Button::Destructive
+                        } else {
+                            theme::Button::Primary
+                        })
+                        .on_press(DeliveryScreenMessage::OptionSelected(i))
+                        .padding(10)
+                        .width(Length::Fill),
+                )
+            },
+        );
+        container(
+            Row::new()
+                .push(
+                    container(
+                        container(
+                            Column::new()
+                                .push(text("What should we do with your photos?").size(42))
+                                .push(options)
+                                .push(text("Press the number of the option you'd like.").size(22))
+                                .width(Length::Fill)
+                                .spacing(16),
+                        )
+                        .style(theme::Container::Custom(Box::new(
+                            RoundedBoxContainerStyle {},
+                        )))
+                        .padding(16)
+                        .max_width(840),
+                    )
+                    .width(Length::Fill)
+                    .align_x(iced::alignment::Horizontal::Center),
+                )
+                .push(Image::new(self.preview_handle.clone()))
+                .align_items(iced::Alignment::Center)
+                .spacing(24),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .padding(20)
+        .into()
+    }
+
+    fn subscription(&self) -> iced::Subscription<DeliveryScreenMessage> {
+        iced::keyboard::on_key_press(|key, _modifiers| match key {
+            iced::keyboard::Key::Character(c) => c
+                .parse::<usize>()
+                .ok()
+                .filter(|number| *number >= 1)
+                .map(|number| DeliveryScreenMessage::OptionSelected(number - 1)),
+            _ => None,
+        })
+    }
+}
+
+impl Into<super::Screen> for DeliveryScreen {
+    fn into(self) -> super::Screen {
+        super::Screen::DeliveryScreen(self)
+    }
+}
@@ -1,4 +1,4 @@
-use std::{str::FromStr, time::Duration};
+use std::{str::FromStr, sync::Arc, time::Duration};
 
 use email_address::EmailAddress;
 use iced::{
@@ -13,9 +13,15 @@ use image::RgbaImage;
 
 use crate::{
     config::Config,
-    utils::container_styles::{OutlinedContainerStyle, RoundedBoxContainerStyle},
+    utils::{
+        container_styles::{OutlinedContainerStyle, RoundedBoxContainerStyle},
+        image_encoding::Attachment,
+        image_metadata::ImageMetadata,
+    },
 };
 
+use super::delivery_screen::{continue_delivery, DeliveryPlan};
+
 fn domain_allowed(domain: &str, config: &Config) -> bool {
     for whitelisted_domain in &config.email_whitelisted_domains {
         if whitelisted_domain == domain || whitelisted_domain == "*" {
@@ -43,7 +49,10 @@ pub(crate) struct EmailScreen {
     index: nokhwa::utils::CameraIndex,
 
     preview_handle: Handle,
-    printable_image: RgbaImage,
+    printable_image: Arc<RgbaImage>,
+    metadata: ImageMetadata,
+    attachments: Vec<Attachment>,
+    delivery_plan: DeliveryPlan,
 
     email_addresses: Vec<String>,
     current_email_address_validity: EmailAddressValidity,
@@ -65,7 +74,10 @@ pub(crate) struct EmailScreenFlags {
     pub index: nokhwa::utils::CameraIndex,
 
     pub preview_handle: Handle,
-    pub printable_image: RgbaImage,
+    pub printable_image: Arc<RgbaImage>,
+    pub metadata: ImageMetadata,
+    pub attachments: Vec<Attachment>,
+    pub delivery_plan: DeliveryPlan,
 }
 
 impl Into<super::ScreenMessage> for EmailScreenMessage {
@@ -85,6 +97,9 @@ impl super::Screenish for EmailScreen {
 
                 preview_handle: flags.preview_handle,
                 printable_image: flags.printable_image,
+                metadata: flags.metadata,
+                attachments: flags.attachments,
+                delivery_plan: flags.delivery_plan,
 
                 email_addresses: Vec::new(),
                 current_email_address_validity: EmailAddressValidity::Invalid,
@@ -131,14 +146,34 @@ impl super::Screenish for EmailScreen {
                             config: self.config.clone(),
                             index: self.index.clone(),
 
+                            preview_handle: self.preview_handle.clone(),
                             image: self.printable_image.clone(),
+                            metadata: self.metadata.clone(),
+                            attachments: self.attachments.clone(),
                             addresses: self.email_addresses.clone(),
+                            delivery_plan: self.delivery_plan.clone(),
                         };
                         return iced::Command::perform(async {}, |_| {
                             super::ScreenMessage::TransitionToScreen(
                                 super::ScreenFlags::SendingScreenFlags(flags),
                             )
                         });
+                    } else if self.delivery_plan.is_combination() {
+                        // Skip emailing but keep going with the other methods
+                        let flags = continue_delivery(
+                            self.config.clone(),
+                            self.index.clone(),
+                            self.preview_handle.clone(),
+                            self.printable_image.clone(),
+                            self.metadata.clone(),
+                            self.attachments.clone(),
+                            self.delivery_plan
+                                .clone()
+                                .done("Your photos weren't emailed."),
+                        );
+                        return iced::Command::perform(async {}, |_| {
+                            super::ScreenMessage::TransitionToScreen(flags)
+                        });
                     } else {
                         // Cancel and discard the photos
                         let flags = super::alert_screen::AlertScreenFlags {
@@ -219,6 +254,8 @@ impl super::Screenish for EmailScreen {
                                                         "Press [Enter] to add email address"
                                                     } else if self.email_addresses.len() > 0 {
                                                         "Press [Enter] to finish"
+                                                    } else if self.delivery_plan.is_combination() {
+                                                        "Press [Enter] to skip"
                                                     } else {
                                                         "Press [Enter] to cancel"
                                                     }
@@ -255,6 +292,8 @@ impl super::Screenish for EmailScreen {
                                             "Everything looks good. Note that by pressing [Enter] and adding your email address to the list, you consent to having your photos processed by the system and saved on our servers."
                                         } else if self.email_addresses.len() > 0 {
                                             "You may add more addresses to send the photo to. Type another one, or press [Enter] to have the photo emailed to the above accounts."
+                                        } else if self.delivery_plan.is_combination() {
+                                            "Enter your email address so we can send you the photos you just took. By entering your email address(es), you consent to having your photos processed by the system and saved on our servers. If you don't want them emailed, press [Enter] now to skip this step."
                                         } else {
                                             "Enter your email address so we can send you the photos you just took. By entering your email address(es), you consent to having your photos processed by the system and saved on our servers. If you do not wish for this to happen, press [Enter] now to cancel and discard your photos."
                                         }
@@ -1,17 +1,41 @@
+mod animated_loop;
+mod frame_attachments;
+pub(super) mod frame_shape;
 mod image_strip_renderer;
+mod normalize;
 
-use std::time::Duration;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use anim::{Animation, Timeline};
 use iced::{
-    widget::{container, image::Handle, text, Column, ProgressBar, Space},
+    futures::{channel::mpsc, stream, StreamExt},
+    theme,
+    widget::{button, container, image::Handle, text, Column, ProgressBar, Row, Space},
     Element, Length,
 };
-use image::RgbaImage;
+use image::{imageops::FilterType, RgbaImage};
 
-use crate::{config::Config, utils::template_image::TEMPLATE_IMAGE};
+use crate::{
+    config::{Config, Template},
+    utils::{
+        color_lut::Lut3d, crop::crop_rect, crop_focus::CropFocus, image_encoding::Attachment,
+        image_metadata::ImageMetadata, template_image::TEMPLATE_IMAGE, text_stamp::StampValues,
+    },
+};
 
-use self::image_strip_renderer::image_strip_renderer;
+use self::{
+    animated_loop::animated_loop_attachment,
+    frame_attachments::frame_attachments,
+    frame_shape::load_frame_masks,
+    image_strip_renderer::{image_strip_renderer, RenderError},
+    normalize::normalize_frames,
+};
 
 fn progress_bar_animation(
     old_value: f32,
@@ -32,10 +56,62 @@ fn progress_bar_animation(
     ])
 }
 
+/// How much of the progress bar is filled by rendering. The rest is filled
+/// once the render has finished.
+const RENDER_PROGRESS: f32 = 0.9;
+
+/// The strip's pixels, shared with the preview instead of copied for it.
+struct SharedPixels(Arc<RgbaImage>);
+
+impl AsRef<[u8]> for SharedPixels {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_raw()
+    }
+}
+
+/// Load the template images and render the final strip.
+fn render(
+    captured_frames: &[Arc<RgbaImage>],
+    focuses: &[(f32, f32)],
+    template: &Template,
+    stamp_values: &StampValues,
+    filter: FilterType,
+    look: Option<&Lut3d>,
+    progress: &(dyn Fn() + Sync),
+) -> Result<RgbaImage, RenderError> {
+    let background = image::load_from_memory(TEMPLATE_IMAGE)
+        .map_err(|e| RenderError::TemplateImage(e.to_string()))?
+        .into_rgba8();
+    let overlay = match &template.overlay {
+        Some(path) => Some(
+            image::open(path)
+                .map_err(|e| RenderError::OverlayImage {
+                    path: path.clone(),
+                    reason: e.to_string(),
+                })?
+                .into_rgba8(),
+        ),
+        None => None,
+    };
+    let masks = load_frame_masks(template)?;
+    image_strip_renderer(
+        background,
+        overlay,
+        &masks,
+        captured_frames,
+        focuses,
+        template,
+        stamp_values,
+        filter,
+        look,
+        progress,
+    )
+}
+
 #[derive(Debug)]
 enum ProcessingState {
     GeneratingImage,
-    GenerateImageFailed,
+    GenerateImageFailed(RenderError),
     GenerateImageFinished,
 }
 
@@ -46,17 +122,25 @@ pub(crate) struct GenerationScreen {
 
     progress_bar_timeline: Timeline<f32>,
 
-    captured_frames: Option<Vec<image::RgbaImage>>,
+    captured_frames: Vec<Arc<RgbaImage>>,
+    look: Option<Arc<Lut3d>>,
+    stamp_values: StampValues,
+    crop_focus: Arc<CropFocus>,
 
     processing_state: ProcessingState,
     preview_handle: Option<Handle>,
-    printable_image: Option<RgbaImage>,
+    printable_image: Option<Arc<RgbaImage>>,
+    attachments: Vec<Attachment>,
 }
 
 #[derive(Debug, Clone)]
 pub enum GenerationScreenMessage {
     GenerateImage,
-    FinishProcessImage(Option<(image::RgbaImage, Handle)>),
+    /// How much of the render is done, from 0 to 1.
+    Progress(f32),
+    FinishProcessImage(Result<(Arc<RgbaImage>, Handle, Vec<Attachment>), RenderError>),
+    RetryPressed,
+    BackToCameraPressed,
     Tick,
 }
 
@@ -65,7 +149,12 @@ pub(crate) struct GenerationScreenFlags {
     pub config: Config,
     pub index: nokhwa::utils::CameraIndex,
 
-    pub captured_frames: Vec<image::RgbaImage>,
+    pub captured_frames: Vec<Arc<RgbaImage>>,
+    /// The filter for the look the guest picked.
+    pub look: Option<Arc<Lut3d>>,
+    pub stamp_values: StampValues,
+    /// The camera screen's, so the face model isn't read again.
+    pub crop_focus: Arc<CropFocus>,
 }
 
 impl Into<super::ScreenMessage> for GenerationScreenMessage {
@@ -81,14 +170,18 @@ impl super::Screenish for GenerationScreen {
         (
             GenerationScreen {
                 processing_state: ProcessingState::GeneratingImage,
-                captured_frames: Some(flags.captured_frames),
+                captured_frames: flags.captured_frames,
+                look: flags.look,
+                stamp_values: flags.stamp_values,
+                crop_focus: flags.crop_focus,
                 config: flags.config,
                 index: flags.index,
 
-                progress_bar_timeline: progress_bar_animation(0.0, 0.8, 3000).to_timeline(),
+                progress_bar_timeline: progress_bar_animation(0.0, 0.0, 0).to_timeline(),
 
                 preview_handle: None,
                 printable_image: None,
+                attachments: vec![],
             },
             Some(GenerationScreenMessage::GenerateImage),
         )
@@ -96,43 +189,152 @@ impl super::Screenish for GenerationScreen {
     fn update(&mut self, message: GenerationScreenMessage) -> iced::Command<super::ScreenMessage> {
         match message {
             GenerationScreenMessage::GenerateImage => {
-                self.progress_bar_timeline.begin();
+                self.processing_state = ProcessingState::GeneratingImage;
+                self.progress_bar_timeline = progress_bar_animation(0.0, 0.0, 0).to_timeline();
                 let template = self.config.template.clone();
-                let maybe_captured_frames = self.captured_frames.take();
-                if let Some(captured_frames) = maybe_captured_frames {
-                    self.processing_state = ProcessingState::GeneratingImage;
-                    iced::Command::perform(
-                        async move {
-                            tokio::task::spawn_blocking(move || {
-                                let rendered = image_strip_renderer(
-                                    image::load_from_memory(TEMPLATE_IMAGE)
-                                        .expect("failed to decode template image")
-                                        .into_rgba8(),
-                                    &captured_frames,
-                                    &template,
-                                );
-                                (
-                                    rendered.clone(),
-                                    Handle::from_pixels(
-                                        rendered.width(),
-                                        rendered.height(),
-                                        rendered.into_raw(),
-                                    ),
-                                )
+                let stamp_values = self.stamp_values.clone();
+                let frame_attachment_settings = self.config.frame_attachments.clone();
+                let animated_loop_settings = self.config.animated_loop.clone();
+                let output_format = self.config.output_format;
+                let filter = self.config.resample_filter.filter_type();
+                let crop_focus = self.crop_focus.clone();
+                let mirror_output = self.config.mirror_output;
+                let normalize = self.config.normalize.clone();
+                let metadata = ImageMetadata::new(&self.config, self.stamp_values.captured_at);
+                // keep the frames around in case the render has to be retried,
+                // they're shared so this doesn't copy them
+                let captured_frames = self.captured_frames.clone();
+                let look = self.look.clone();
+
+                // every frame in the strip, every frame attachment and the
+                // animated loop is a step
+                let steps = captured_frames.len()
+                    + frame_attachment_settings
+                        .as_ref()
+                        .map_or(0, |_| captured_frames.len())
+                    + animated_loop_settings.as_ref().map_or(0, |_| 1);
+                let (progress_sender, progress_receiver) = mpsc::unbounded();
+                let render = async move {
+                    tokio::task::spawn_blocking(move || {
+                        let done = AtomicUsize::new(0);
+                        let progress = || {
+                            let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                            // the screen may have moved on, so a failed send is fine
+                            let _ =
+                                progress_sender.unbounded_send(GenerationScreenMessage::Progress(
+                                    done as f32 / steps.max(1) as f32,
+                                ));
+                        };
+                        // the frames were mirrored when they were captured
+                        let focuses: Vec<_> = captured_frames
+                            .iter()
+                            .map(|frame| {
+                                if mirror_output {
+                                    crop_focus.mirrored_focus(frame)
+                                } else {
+                                    crop_focus.focus(frame)
+                                }
                             })
-                            .await
-                            .ok()
-                        },
-                        GenerationScreenMessage::FinishProcessImage,
+                            .collect();
+                        // correct the frames by what will be seen of them
+                        let captured_frames = match &normalize {
+                            Some(settings) => {
+                                let windows: Vec<_> = captured_frames
+                                    .iter()
+                                    .zip(&focuses)
+                                    .enumerate()
+                                    .map(|(i, (frame, focus))| {
+                                        crop_rect(
+                                            frame.width(),
+                                            frame.height(),
+                                            template
+                                                .frames
+                                                .get(i)
+                                                .map(|frame| frame.width / frame.height),
+                                            *focus,
+                                        )
+                                    })
+                                    .collect();
+                                normalize_frames(&captured_frames, &windows, settings)
+                            }
+                            None => captured_frames,
+                        };
+                        let rendered = render(
+                            &captured_frames,
+                            &focuses,
+                            &template,
+                            &stamp_values,
+                            filter,
+                            look.as_deref(),
+                            &progress,
+                        )?;
+                        let mut attachments = match frame_attachment_settings {
+                            Some(settings) => frame_attachments(
+                                &captured_frames,
+                                &focuses,
+                                &template,
+                                &settings,
+                                output_format,
+                                &metadata,
+                                filter,
+                                look.as_deref(),
+                                &progress,
+                            ),
+                            None => vec![],
+                        };
+                        if let Some(settings) = animated_loop_settings {
+                            // the strip still goes out without it
+                            match animated_loop_attachment(
+                                &captured_frames,
+                                &focuses,
+                                &template,
+                                &settings,
+                                &metadata,
+                                filter,
+                                look.as_deref(),
+                            ) {
+                                Ok(attachment) => attachments.push(attachment),
+                                Err(e) => {
+                                    eprintln!("warning: leaving out the animated loop: {}", e)
+                                }
+                            }
+                            progress();
+                        }
+                        let rendered = Arc::new(rendered);
+                        let handle = Handle::from_pixels(
+                            rendered.width(),
+                            rendered.height(),
+                            SharedPixels(rendered.clone()),
+                        );
+                        Ok((rendered, handle, attachments))
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(RenderError::Task(e.to_string())))
+                };
+                iced::Command::run(
+                    stream::select(
+                        progress_receiver,
+                        stream::once(render).map(GenerationScreenMessage::FinishProcessImage),
+                    ),
+                    super::ScreenMessage::GenerationScreenMessage,
+                )
+            }
+            GenerationScreenMessage::Progress(progress) => {
+                // progress can arrive after the render has finished
+                if matches!(self.processing_state, ProcessingState::GeneratingImage) {
+                    self.progress_bar_timeline = progress_bar_animation(
+                        self.progress_bar_timeline.value(),
+                        progress * RENDER_PROGRESS,
+                        250,
                     )
-                    .map(super::ScreenMessage::GenerationScreenMessage)
-                } else {
-                    eprintln!("warning: GenerationScreenMessage::GenerateImage called while there was no captured frame data");
-                    iced::Command::none()
+                    .to_timeline();
+                    self.progress_bar_timeline.begin();
                 }
+                iced::Command::none()
             }
-            GenerationScreenMessage::FinishProcessImage(Some((rendered, handle))) => {
+            GenerationScreenMessage::FinishProcessImage(Ok((rendered, handle, attachments))) => {
                 self.printable_image = Some(rendered);
+                self.attachments = attachments;
                 self.preview_handle = Some(handle);
                 self.processing_state = ProcessingState::GenerateImageFinished;
                 self.progress_bar_timeline =
@@ -141,10 +343,38 @@ impl super::Screenish for GenerationScreen {
                 self.progress_bar_timeline.begin();
                 iced::Command::none()
             }
-            GenerationScreenMessage::FinishProcessImage(None) => {
-                self.processing_state = ProcessingState::GenerateImageFailed;
+            GenerationScreenMessage::FinishProcessImage(Err(error)) => {
+                eprintln!("failed to generate image: {}", error);
+                self.processing_state = ProcessingState::GenerateImageFailed(error);
+                self.progress_bar_timeline = progress_bar_animation(0.0, 0.0, 0).to_timeline();
                 iced::Command::none()
             }
+            GenerationScreenMessage::RetryPressed => {
+                if !matches!(
+                    self.processing_state,
+                    ProcessingState::GenerateImageFailed(_)
+                ) {
+                    return iced::Command::none();
+                }
+                self.update(GenerationScreenMessage::GenerateImage)
+            }
+            GenerationScreenMessage::BackToCameraPressed => {
+                if !matches!(
+                    self.processing_state,
+                    ProcessingState::GenerateImageFailed(_)
+                ) {
+                    return iced::Command::none();
+                }
+                let flags = super::camera_screen::CameraScreenFlags {
+                    config: self.config.clone(),
+                    index: self.index.clone(),
+                };
+                iced::Command::perform(async {}, |_| {
+                    super::ScreenMessage::TransitionToScreen(super::ScreenFlags::CameraScreenFlags(
+                        flags,
+                    ))
+                })
+            }
             GenerationScreenMessage::Tick => {
                 self.progress_bar_timeline.update();
                 if self.progress_bar_timeline.status().is_completed()
@@ -160,16 +390,17 @@ impl super::Screenish for GenerationScreen {
                         .printable_image
                         .clone()
                         .expect("printable image is None when progress bar is finished");
+                    let metadata = ImageMetadata::new(&self.config, self.stamp_values.captured_at);
+                    let attachments = std::mem::take(&mut self.attachments);
                     return iced::Command::perform(
                         async {
-                            super::ScreenFlags::EmailScreenFlags(
-                                super::email_screen::EmailScreenFlags {
-                                    config,
-                                    index,
-
-                                    preview_handle,
-                                    printable_image,
-                                },
+                            super::delivery_screen::delivery_flags(
+                                config,
+                                index,
+                                preview_handle,
+                                printable_image,
+                                metadata,
+                                attachments,
                             )
                         },
                         super::ScreenMessage::TransitionToScreen,
@@ -180,22 +411,36 @@ impl super::Screenish for GenerationScreen {
         }
     }
     fn view(&self) -> Element<GenerationScreenMessage> {
-        container(
-            Column::new()
+        let content = match &self.processing_state {
+            ProcessingState::GenerateImageFailed(error) => Column::new()
+                .push(text("Something went wrong making your photo strip.").size(46))
+                .push(text(error.to_string()).size(20))
+                .push(Space::with_height(24))
                 .push(
-                    text(match self.processing_state {
-                        ProcessingState::GeneratingImage => "Processing your photos...",
-                        ProcessingState::GenerateImageFinished => "Processing your photos...",
-                        ProcessingState::GenerateImageFailed => "Failed to generate your image.",
-                    })
-                    .size(46),
-                )
+                    Row::new()
+                        .push(
+                            button(text("Press [Enter] to try again").size(24))
+                                .style(theme::Button::Primary)
+                                .on_press(GenerationScreenMessage::RetryPressed),
+                        )
+                        .push(
+                            button(text("Press [Esc] to retake your photos").size(24))
+                                .style(theme::Button::Destructive)
+                                .on_press(GenerationScreenMessage::BackToCameraPressed),
+                        )
+                        .spacing(16),
+                ),
+            _ => Column::new()
+                .push(text("Processing your photos...").size(46))
                 .push(Space::with_height(24))
                 .push(
                     ProgressBar::new(0.0..=1.0, self.progress_bar_timeline.value())
                         .height(16)
                         .width(460),
-                )
+                ),
+        };
+        container(
+            content
                 .align_items(iced::Alignment::Center)
                 .width(Length::Fill),
         )
@@ -207,13 +452,32 @@ impl super::Screenish for GenerationScreen {
     }
 
     fn subscription(&self) -> iced::Subscription<GenerationScreenMessage> {
-        if self.progress_bar_timeline.status().is_animating() {
-            const FPS: f32 = 60.0;
-            iced::time::every(Duration::from_secs_f32(1.0 / FPS))
-                .map(|_tick| GenerationScreenMessage::Tick)
-        } else {
-            iced::Subscription::none()
-        }
+        iced::Subscription::batch([
+            if matches!(
+                self.processing_state,
+                ProcessingState::GenerateImageFailed(_)
+            ) {
+                iced::keyboard::on_key_press(|key, _modifiers| match key {
+                    iced::keyboard::Key::Named(iced::keyboard::key::Named::Space)
+                    | iced::keyboard::Key::Named(iced::keyboard::key::Named::Enter) => {
+                        Some(GenerationScreenMessage::RetryPressed)
+                    }
+                    iced::keyboard::Key::Named(iced::keyboard::key::Named::Escape) => {
+                        Some(GenerationScreenMessage::BackToCameraPressed)
+                    }
+                    _ => None,
+                })
+            } else {
+                iced::Subscription::none()
+            },
+            if self.progress_bar_timeline.status().is_animating() {
+                const FPS: f32 = 60.0;
+                iced::time::every(Duration::from_secs_f32(1.0 / FPS))
+                    .map(|_tick| GenerationScreenMessage::Tick)
+            } else {
+                iced::Subscription::none()
+            },
+        ])
     }
 }
 
@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use image::{imageops, RgbaImage};
+
+use crate::{
+    config::{AnimatedLoop, Template},
+    utils::{
+        color_lut::Lut3d,
+        image_encoding::{encode_animation, Attachment},
+        image_metadata::ImageMetadata,
+    },
+};
+
+use super::image_strip_renderer::{crop_to_aspect_ratio, resize, RenderError};
+
+/// Make a looping animation of the captured frames. Every frame is cropped to
+/// the aspect ratio of the template's first frame so they line up, and
+/// filtered with the `look`.
+pub(super) fn animated_loop_attachment(
+    captured_frames: &[Arc<RgbaImage>],
+    focuses: &[(f32, f32)],
+    template: &Template,
+    settings: &AnimatedLoop,
+    metadata: &ImageMetadata,
+    filter: imageops::FilterType,
+    look: Option<&Lut3d>,
+) -> Result<Attachment, RenderError> {
+    let aspect_ratio = template.frames[0].width / template.frames[0].height;
+    let max_size = settings.max_size.max(1) as f32;
+    let (width, height) = if aspect_ratio >= 1.0 {
+        (max_size, max_size / aspect_ratio)
+    } else {
+        (max_size * aspect_ratio, max_size)
+    };
+    let (width, height) = (
+        (width.round() as u32).max(1),
+        (height.round() as u32).max(1),
+    );
+    let mut frames: Vec<RgbaImage> = captured_frames
+        .iter()
+        .zip(focuses)
+        .map(|(frame, focus)| {
+            let mut frame = resize(
+                &crop_to_aspect_ratio(frame, aspect_ratio, *focus),
+                width,
+                height,
+                filter,
+            );
+            if let Some(lut) = look {
+                lut.apply(&mut frame);
+            }
+            frame
+        })
+        .collect();
+    if settings.boomerang && frames.len() > 2 {
+        // play back without repeating the first and last frames
+        let backwards: Vec<_> = frames[1..frames.len() - 1].iter().rev().cloned().collect();
+        frames.extend(backwards);
+    }
+    Ok(Attachment {
+        filename: format!("animation.{}", settings.format.extension()),
+        mime: settings.format.mime(),
+        data: encode_animation(&frames, settings.format, settings.frame_delay, metadata)
+            .map_err(RenderError::Encode)?
+            .into(),
+    })
+}
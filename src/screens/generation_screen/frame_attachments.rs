@@ -0,0 +1,100 @@
+use std::{borrow::Cow, sync::Arc};
+
+use image::{imageops, RgbaImage};
+
+use crate::{
+    config::{FrameAttachments, OutputFormat, Template},
+    utils::{
+        color_lut::Lut3d,
+        image_encoding::{encode_image, Attachment},
+        image_metadata::ImageMetadata,
+    },
+};
+
+use super::image_strip_renderer::{crop_to_aspect_ratio, resize, RenderError};
+
+/// Scale `frame` down so its longest side is at most `max_size`.
+fn limit_size(
+    frame: Cow<RgbaImage>,
+    max_size: u32,
+    filter: imageops::FilterType,
+) -> Cow<RgbaImage> {
+    let (width, height) = frame.dimensions();
+    let longest = width.max(height);
+    if longest <= max_size || max_size == 0 {
+        return frame;
+    }
+    let scale = max_size as f32 / longest as f32;
+    Cow::Owned(resize(
+        frame.as_ref(),
+        ((width as f32 * scale).round() as u32).max(1),
+        ((height as f32 * scale).round() as u32).max(1),
+        filter,
+    ))
+}
+
+/// Encode the captured frames on their own, to be sent along with the strip.
+/// They use the output format, except that PDFs become JPEGs since a PDF per
+/// photo isn't useful. The `look` is applied to each one, and `progress` is
+/// called as each one is done. Photos that fail to encode are left out with a
+/// warning, since the strip is what matters.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn frame_attachments(
+    captured_frames: &[Arc<RgbaImage>],
+    focuses: &[(f32, f32)],
+    template: &Template,
+    settings: &FrameAttachments,
+    output_format: OutputFormat,
+    metadata: &ImageMetadata,
+    filter: imageops::FilterType,
+    look: Option<&Lut3d>,
+    progress: &(dyn Fn() + Sync),
+) -> Vec<Attachment> {
+    let format = match output_format {
+        OutputFormat::Pdf { quality } => OutputFormat::Jpeg { quality },
+        format => format,
+    };
+    captured_frames
+        .iter()
+        .zip(focuses)
+        .zip(&template.frames)
+        .enumerate()
+        .filter_map(|(i, ((frame, focus), template_frame))| {
+            let frame = if settings.crop {
+                Cow::Owned(crop_to_aspect_ratio(
+                    frame,
+                    template_frame.width / template_frame.height,
+                    *focus,
+                ))
+            } else {
+                Cow::Borrowed(frame.as_ref())
+            };
+            let frame = limit_size(frame, settings.max_size, filter);
+            let frame = match look {
+                Some(lut) => {
+                    let mut frame = frame.into_owned();
+                    lut.apply(&mut frame);
+                    Cow::Owned(frame)
+                }
+                None => frame,
+            };
+            let attachment = match encode_image(&frame, format, template.dpi, metadata) {
+                Ok(data) => Some(Attachment {
+                    filename: format!("photo-{}.{}", i + 1, format.extension()),
+                    mime: format.mime(),
+                    data: data.into(),
+                }),
+                Err(e) => {
+                    eprintln!(
+                        "warning: leaving out photo {}: {}",
+                        i + 1,
+                        RenderError::Encode(e)
+                    );
+                    None
+                }
+            };
+            progress();
+            attachment
+        })
+        .collect()
+}
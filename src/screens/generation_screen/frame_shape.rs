@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use image::{imageops, GrayImage, RgbaImage};
+
+use crate::{
+    config::{FrameShape, Template},
+    utils::border_radius::{self, BorderRadius},
+};
+
+use super::image_strip_renderer::RenderError;
+
+/// Subpixel samples per axis used to anti-alias ellipse edges.
+const ELLIPSE_SAMPLES: u32 = 4;
+
+/// The mask images of a template's frames, by path.
+pub(crate) type FrameMasks = HashMap<String, GrayImage>;
+
+/// Open the mask images used by `template`'s frames. Frames often share a
+/// mask, so each one is only opened once.
+pub(crate) fn load_frame_masks(template: &Template) -> Result<FrameMasks, RenderError> {
+    let mut masks = FrameMasks::new();
+    for frame in &template.frames {
+        if let Some(FrameShape::Mask { path }) = &frame.shape {
+            if masks.contains_key(path) {
+                continue;
+            }
+            let mask = image::open(path).map_err(|e| RenderError::FrameMask {
+                path: path.clone(),
+                reason: e.to_string(),
+            })?;
+            masks.insert(path.clone(), mask.into_luma8());
+        }
+    }
+    Ok(masks)
+}
+
+/// Multiply the alpha channel of `frame` with a coverage value in `0..=255`.
+fn multiply_alpha(frame: &mut RgbaImage, coverage: impl Fn(u32, u32) -> u8) {
+    for (x, y, pixel) in frame.enumerate_pixels_mut() {
+        pixel.0[3] = ((pixel.0[3] as u16 * coverage(x, y) as u16 + 127) / 255) as u8;
+    }
+}
+
+fn ellipse(frame: &mut RgbaImage) {
+    let (width, height) = frame.dimensions();
+    let radius_x = width as f32 / 2.0;
+    let radius_y = height as f32 / 2.0;
+    let samples = ELLIPSE_SAMPLES * ELLIPSE_SAMPLES;
+    multiply_alpha(frame, |x, y| {
+        let mut inside = 0;
+        for sub_y in 0..ELLIPSE_SAMPLES {
+            for sub_x in 0..ELLIPSE_SAMPLES {
+                let dx = (x as f32 + (sub_x as f32 + 0.5) / ELLIPSE_SAMPLES as f32 - radius_x)
+                    / radius_x;
+                let dy = (y as f32 + (sub_y as f32 + 0.5) / ELLIPSE_SAMPLES as f32 - radius_y)
+                    / radius_y;
+                if dx * dx + dy * dy <= 1.0 {
+                    inside += 1;
+                }
+            }
+        }
+        (inside * 255 / samples) as u8
+    });
+}
+
+/// Cut `frame`, which has already been resized to its place in the strip, to
+/// `shape`. `scale` is the number of pixels per template unit, and `masks`
+/// are the template's masks from [`load_frame_masks`].
+pub(crate) fn apply_frame_shape(
+    frame: &mut RgbaImage,
+    shape: &FrameShape,
+    scale: f32,
+    masks: &FrameMasks,
+) -> Result<(), RenderError> {
+    let (width, height) = frame.dimensions();
+    match shape {
+        FrameShape::Rounded { radius } => {
+            // the corners can't overlap
+            let radius = ((radius * scale).round() as u32).min(width.min(height) / 2);
+            border_radius::round(frame, &BorderRadius::from(radius));
+        }
+        FrameShape::Ellipse => ellipse(frame),
+        FrameShape::Mask { path } => {
+            let mask = masks.get(path).ok_or_else(|| RenderError::FrameMask {
+                path: path.clone(),
+                reason: "it wasn't loaded with the template".to_string(),
+            })?;
+            // Triangle is plenty for a mask and keeps its edges soft
+            let mask = imageops::resize(mask, width, height, imageops::FilterType::Triangle);
+            multiply_alpha(frame, |x, y| mask.get_pixel(x, y).0[0]);
+        }
+    }
+    Ok(())
+}
@@ -1,55 +1,197 @@
+use std::sync::Arc;
+
 use image::imageops;
 
-use crate::config::Template;
+use crate::{
+    config::{Template, TemplateUnits},
+    utils::{
+        color_lut::Lut3d,
+        crop::crop_rect,
+        text_stamp::{stamp_texts, StampValues},
+    },
+};
+
+use super::frame_shape::{apply_frame_shape, FrameMasks};
+
+/// Why a strip couldn't be rendered.
+#[derive(Debug, Clone)]
+pub enum RenderError {
+    /// More frames were captured than the template has room for.
+    TooManyFrames { captured: usize, template: usize },
+    /// The built-in template image couldn't be decoded.
+    TemplateImage(String),
+    /// The template overlay image couldn't be opened.
+    OverlayImage { path: String, reason: String },
+    /// A frame mask image couldn't be opened.
+    FrameMask { path: String, reason: String },
+    /// An extra output couldn't be encoded.
+    Encode(String),
+    /// The render task panicked or was cancelled.
+    Task(String),
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::TooManyFrames { captured, template } => write!(
+                f,
+                "{} frames were captured but the template only has {}",
+                captured, template
+            ),
+            RenderError::TemplateImage(reason) => {
+                write!(f, "failed to decode the template image: {}", reason)
+            }
+            RenderError::OverlayImage { path, reason } => {
+                write!(f, "failed to open the overlay image {:?}: {}", path, reason)
+            }
+            RenderError::FrameMask { path, reason } => {
+                write!(
+                    f,
+                    "failed to open the frame mask image {:?}: {}",
+                    path, reason
+                )
+            }
+            RenderError::Encode(reason) => write!(f, "failed to encode an attachment: {}", reason),
+            RenderError::Task(reason) => write!(f, "the render task failed: {}", reason),
+        }
+    }
+}
+
+/// Cut the sides or the top and bottom off `frame` so it has `aspect_ratio`,
+/// keeping it centred on `focus` as far as possible.
+pub(super) fn crop_to_aspect_ratio(
+    frame: &image::RgbaImage,
+    aspect_ratio: f32,
+    focus: (f32, f32),
+) -> image::RgbaImage {
+    let (left, top, width, height) =
+        crop_rect(frame.width(), frame.height(), Some(aspect_ratio), focus);
+    imageops::crop_imm(frame, left, top, width, height).to_image()
+}
+
+/// Scale `image` to `width` by `height`. Big reductions are done in stages,
+/// halving the image with a cheap filter first, so `filter` only ever has to
+/// look at a few source pixels for each output pixel.
+pub(super) fn resize(
+    image: &image::RgbaImage,
+    width: u32,
+    height: u32,
+    filter: imageops::FilterType,
+) -> image::RgbaImage {
+    let mut halved = None;
+    loop {
+        let current: &image::RgbaImage = halved.as_ref().unwrap_or(image);
+        // an empty target would otherwise keep halving forever
+        if current.width() < width.max(1) * 4 || current.height() < height.max(1) * 4 {
+            break;
+        }
+        halved = Some(imageops::resize(
+            current,
+            current.width() / 2,
+            current.height() / 2,
+            imageops::FilterType::Triangle,
+        ));
+    }
+    imageops::resize(halved.as_ref().unwrap_or(image), width, height, filter)
+}
 
-/// # Panics
-///
-/// Panics if the captured_frames count exceeds the amount in the template.
+/// Render the strip. Frames are rendered in parallel, cropped around their
+/// `focuses` and filtered with the `look`, and `progress` is called as each
+/// one is done.
+#[allow(clippy::too_many_arguments)]
 pub(super) fn image_strip_renderer<'a>(
     mut background: image::RgbaImage,
-    captured_frames: &'a Vec<image::RgbaImage>,
+    overlay: Option<image::RgbaImage>,
+    masks: &'a FrameMasks,
+    captured_frames: &'a [Arc<image::RgbaImage>],
+    focuses: &'a [(f32, f32)],
     template: &'a Template,
-) -> image::RgbaImage {
+    stamp_values: &StampValues,
+    filter: imageops::FilterType,
+    look: Option<&Lut3d>,
+    progress: &(dyn Fn() + Sync),
+) -> Result<image::RgbaImage, RenderError> {
     if captured_frames.len() > template.frames.len() {
-        panic!("captured_frames count exceeds number of frames in template");
+        return Err(RenderError::TooManyFrames {
+            captured: captured_frames.len(),
+            template: template.frames.len(),
+        });
     }
-    for (i, frame) in captured_frames.iter().enumerate() {
-        let template_frame = &template.frames[i];
-
-        // crop the frame to the template
-        let template_aspect_ratio = template_frame.width / template_frame.height;
-        let frame_aspect_ratio = frame.width() as f32 / frame.height() as f32;
-        let mut frame = if template_aspect_ratio < frame_aspect_ratio {
-            // trim off left and right
-            let new_height = frame.height();
-            let new_width = (frame.height() as f32 * template_aspect_ratio) as u32;
-            let left_offset = (frame.width() - new_width) / 2;
-            imageops::crop_imm(frame, left_offset, 0, new_width, new_height).to_image()
-        } else if template_aspect_ratio > frame_aspect_ratio {
-            // trim off top and bottom
-            let new_width = frame.width();
-            let new_height = (frame.width() as f32 / template_aspect_ratio) as u32;
-            let top_offset = (frame.height() - new_height) / 2;
-            imageops::crop_imm(frame, 0, top_offset, new_width, new_height).to_image()
-        } else {
-            // perfect aspect ratio!
-            frame.clone()
-        };
-
-        // resize the frame
-        frame = imageops::resize(
-            &frame,
-            template_frame.width as u32,
-            template_frame.height as u32,
-            imageops::FilterType::Lanczos3,
-        );
 
+    // scale the background to the output resolution. Pixel templates are
+    // measured on the template image, so it keeps its own size
+    let (output_width, output_height) = match template.units {
+        TemplateUnits::Pixels => background.dimensions(),
+        _ => template.output_size(),
+    };
+    if background.dimensions() != (output_width, output_height) {
+        background = resize(&background, output_width, output_height, filter);
+    }
+    let scale = template.pixels_per_unit();
+
+    let frames = std::thread::scope(|scope| {
+        let renders: Vec<_> = captured_frames
+            .iter()
+            .zip(focuses)
+            .zip(&template.frames)
+            .map(|((frame, focus), template_frame)| {
+                scope.spawn(move || {
+                    // crop the frame to the template
+                    let frame = crop_to_aspect_ratio(
+                        frame,
+                        template_frame.width / template_frame.height,
+                        *focus,
+                    );
+
+                    // resize the frame
+                    let mut frame = resize(
+                        &frame,
+                        (template_frame.width * scale).round() as u32,
+                        (template_frame.height * scale).round() as u32,
+                        filter,
+                    );
+
+                    // filter after resizing, there are fewer pixels by then
+                    if let Some(lut) = look {
+                        lut.apply(&mut frame);
+                    }
+
+                    if let Some(shape) = &template_frame.shape {
+                        apply_frame_shape(&mut frame, shape, scale, masks)?;
+                    }
+                    progress();
+                    Ok(frame)
+                })
+            })
+            .collect();
+        renders
+            .into_iter()
+            .map(|render| {
+                render.join().unwrap_or_else(|_| {
+                    Err(RenderError::Task(
+                        "a frame render thread panicked".to_string(),
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, RenderError>>()
+    })?;
+
+    for (frame, template_frame) in frames.iter().zip(&template.frames) {
         imageops::overlay(
             &mut background,
-            &frame,
-            template_frame.x as i64,
-            template_frame.y as i64,
+            frame,
+            (template_frame.x * scale).round() as i64,
+            (template_frame.y * scale).round() as i64,
         )
     }
-    background
+
+    if let Some(mut overlay) = overlay {
+        if overlay.dimensions() != (output_width, output_height) {
+            overlay = resize(&overlay, output_width, output_height, filter);
+        }
+        imageops::overlay(&mut background, &overlay, 0, 0);
+    }
+
+    stamp_texts(&mut background, template, stamp_values);
+    Ok(background)
 }
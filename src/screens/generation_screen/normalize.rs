@@ -0,0 +1,299 @@
+use std::sync::Arc;
+
+use image::{Rgba, RgbaImage};
+
+use crate::config::{Normalize, NormalizeMode};
+
+/// Gains are limited to this factor either way, so a photo that's nearly all
+/// one colour isn't pushed to the opposite one.
+const MAX_GAIN: f32 = 2.0;
+/// The smallest range of brightness that's stretched to the full range, so
+/// a nearly flat photo doesn't turn into noise.
+const MIN_LEVELS_RANGE: f32 = 0.1;
+
+fn luma([r, g, b]: [f32; 3]) -> f32 {
+    0.299 * r + 0.587 * g + 0.114 * b
+}
+
+/// The part of a frame that ends up in the strip, as `(left, top, width,
+/// height)`.
+pub(super) type Window = (u32, u32, u32, u32);
+
+/// The pixels of `frame` inside `window`.
+fn window_pixels(
+    frame: &RgbaImage,
+    (left, top, width, height): Window,
+) -> impl Iterator<Item = &Rgba<u8>> {
+    frame
+        .rows()
+        .skip(top as usize)
+        .take(height as usize)
+        .flat_map(move |row| row.skip(left as usize).take(width as usize))
+}
+
+/// The average of each channel of `frame` inside `window`, from 0 to 1.
+fn channel_means(frame: &RgbaImage, window: Window) -> [f32; 3] {
+    let mut sums = [0u64; 3];
+    let mut count = 0u64;
+    for pixel in window_pixels(frame, window) {
+        for (sum, value) in sums.iter_mut().zip(pixel.0) {
+            *sum += value as u64;
+        }
+        count += 1;
+    }
+    let count = count.max(1) as f32;
+    sums.map(|sum| sum as f32 / count / 255.0)
+}
+
+/// Gains that make `means` average out to grey.
+fn grey_world_gains(means: [f32; 3]) -> [f32; 3] {
+    let grey = (means[0] + means[1] + means[2]) / 3.0;
+    means.map(|mean| (grey / mean.max(f32::EPSILON)).clamp(1.0 / MAX_GAIN, MAX_GAIN))
+}
+
+/// How many pixels of `frame` inside `window` have each brightness, once
+/// `gains` have been applied.
+fn luma_histogram(frame: &RgbaImage, window: Window, gains: [f32; 3]) -> [u64; 256] {
+    let mut histogram = [0; 256];
+    for pixel in window_pixels(frame, window) {
+        let color = [0, 1, 2].map(|channel| pixel.0[channel] as f32 / 255.0 * gains[channel]);
+        histogram[(luma(color).clamp(0.0, 1.0) * 255.0).round() as usize] += 1;
+    }
+    histogram
+}
+
+/// The darkest and brightest levels of `histogram`, leaving out `clip` of
+/// the pixels at each end.
+fn levels(histogram: &[u64; 256], clip: f32) -> (f32, f32) {
+    let total: u64 = histogram.iter().sum();
+    let clipped = (total as f32 * clip.clamp(0.0, 0.5)) as u64;
+    let mut count = 0;
+    let low = histogram
+        .iter()
+        .position(|pixels| {
+            count += pixels;
+            count > clipped
+        })
+        .unwrap_or(0);
+    count = 0;
+    let high = 255
+        - histogram
+            .iter()
+            .rev()
+            .position(|pixels| {
+                count += pixels;
+                count > clipped
+            })
+            .unwrap_or(0);
+    let (low, high) = (low as f32 / 255.0, high as f32 / 255.0);
+    if high - low < MIN_LEVELS_RANGE {
+        // stretch as far as is allowed, around the middle of the range
+        let middle =
+            ((low + high) / 2.0).clamp(MIN_LEVELS_RANGE / 2.0, 1.0 - MIN_LEVELS_RANGE / 2.0);
+        (
+            middle - MIN_LEVELS_RANGE / 2.0,
+            middle + MIN_LEVELS_RANGE / 2.0,
+        )
+    } else {
+        (low, high)
+    }
+}
+
+/// Apply `gains` to `frame` and then stretch `low` to `high` to the full
+/// range.
+fn correct(frame: &RgbaImage, gains: [f32; 3], (low, high): (f32, f32)) -> RgbaImage {
+    // every channel only has 256 values, so work them all out once
+    let tables = gains.map(|gain| {
+        let mut table = [0u8; 256];
+        for (value, output) in table.iter_mut().enumerate() {
+            let value = (value as f32 / 255.0 * gain - low) / (high - low);
+            *output = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+        table
+    });
+    let mut frame = frame.clone();
+    for pixel in frame.pixels_mut() {
+        for channel in 0..3 {
+            pixel.0[channel] = tables[channel][pixel.0[channel] as usize];
+        }
+    }
+    frame
+}
+
+/// Correct the exposure and white balance of the captured frames with
+/// auto-levels and grey-world white balance. Each frame is measured inside
+/// its `windows`, so parts the template crops away don't count.
+pub(super) fn normalize_frames(
+    captured_frames: &[Arc<RgbaImage>],
+    windows: &[Window],
+    settings: &Normalize,
+) -> Vec<Arc<RgbaImage>> {
+    // matching the frames is still worth doing without any other correction
+    let nothing_to_do = settings.mode == NormalizeMode::Independent
+        && !settings.auto_levels
+        && !settings.white_balance;
+    if captured_frames.is_empty() || nothing_to_do {
+        return captured_frames.to_vec();
+    }
+    let means: Vec<[f32; 3]> = captured_frames
+        .iter()
+        .zip(windows)
+        .map(|(frame, window)| channel_means(frame, *window))
+        .collect();
+    let frame_count = means.len() as f32;
+    let session_means = means.iter().fold([0.0; 3], |sum, frame_means| {
+        [0, 1, 2].map(|channel| sum[channel] + frame_means[channel] / frame_count)
+    });
+
+    // the gains for each frame, first to match the session if the frames are
+    // matched and then to balance the colour
+    let gains: Vec<[f32; 3]> = means
+        .iter()
+        .map(|means| {
+            let (matching, balanced_means) = match settings.mode {
+                NormalizeMode::Independent => ([1.0; 3], *means),
+                NormalizeMode::Matched => (
+                    [0, 1, 2].map(|channel| {
+                        (session_means[channel] / means[channel].max(f32::EPSILON))
+                            .clamp(1.0 / MAX_GAIN, MAX_GAIN)
+                    }),
+                    session_means,
+                ),
+            };
+            let balance = if settings.white_balance {
+                grey_world_gains(balanced_means)
+            } else {
+                [1.0; 3]
+            };
+            [0, 1, 2].map(|channel| matching[channel] * balance[channel])
+        })
+        .collect();
+
+    let levels: Vec<(f32, f32)> = if settings.auto_levels {
+        let histograms: Vec<[u64; 256]> = captured_frames
+            .iter()
+            .zip(windows)
+            .zip(&gains)
+            .map(|((frame, window), gains)| luma_histogram(frame, *window, *gains))
+            .collect();
+        match settings.mode {
+            NormalizeMode::Independent => histograms
+                .iter()
+                .map(|histogram| levels(histogram, settings.clip))
+                .collect(),
+            NormalizeMode::Matched => {
+                // level the session as a whole, so every frame gets the same
+                let mut session_histogram = [0; 256];
+                for histogram in &histograms {
+                    for (total, pixels) in session_histogram.iter_mut().zip(histogram) {
+                        *total += pixels;
+                    }
+                }
+                vec![levels(&session_histogram, settings.clip); captured_frames.len()]
+            }
+        }
+    } else {
+        vec![(0.0, 1.0); captured_frames.len()]
+    };
+
+    captured_frames
+        .iter()
+        .zip(gains)
+        .zip(levels)
+        .map(|((frame, gains), levels)| Arc::new(correct(frame, gains, levels)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use image::{Rgba, RgbaImage};
+
+    use super::{channel_means, normalize_frames, Window};
+    use crate::config::{Normalize, NormalizeMode};
+
+    fn settings(auto_levels: bool, white_balance: bool, mode: NormalizeMode) -> Normalize {
+        Normalize {
+            auto_levels,
+            white_balance,
+            clip: 0.01,
+            mode,
+        }
+    }
+
+    fn flat(r: u8, g: u8, b: u8) -> Arc<RgbaImage> {
+        Arc::new(RgbaImage::from_pixel(16, 16, Rgba([r, g, b, 255])))
+    }
+
+    fn whole(frame: &RgbaImage) -> Window {
+        (0, 0, frame.width(), frame.height())
+    }
+
+    fn assert_close(actual: [f32; 3], expected: [f32; 3]) {
+        for channel in 0..3 {
+            assert!(
+                (actual[channel] - expected[channel]).abs() <= 1.0 / 255.0,
+                "{actual:?} isn't close to {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn flat_grey_frame_is_unchanged() {
+        let grey = flat(128, 128, 128);
+        let corrected = normalize_frames(
+            std::slice::from_ref(&grey),
+            &[whole(&grey)],
+            &settings(true, true, NormalizeMode::Independent),
+        );
+        let means = channel_means(&corrected[0], whole(&grey));
+        assert_close(means, channel_means(&grey, whole(&grey)));
+    }
+
+    #[test]
+    fn colour_cast_is_pulled_back_to_neutral() {
+        let warm = flat(150, 100, 80);
+        let corrected = normalize_frames(
+            std::slice::from_ref(&warm),
+            &[whole(&warm)],
+            &settings(false, true, NormalizeMode::Independent),
+        );
+        let [r, g, b] = channel_means(&corrected[0], whole(&warm));
+        assert_close([r, g, b], [g; 3]);
+    }
+
+    #[test]
+    fn matched_frames_have_the_same_mean() {
+        let dark = flat(80, 80, 80);
+        let bright = flat(160, 160, 160);
+        let corrected = normalize_frames(
+            &[dark.clone(), bright.clone()],
+            &[whole(&dark), whole(&bright)],
+            &settings(false, false, NormalizeMode::Matched),
+        );
+        assert_close(
+            channel_means(&corrected[0], whole(&dark)),
+            channel_means(&corrected[1], whole(&bright)),
+        );
+    }
+
+    #[test]
+    fn pixels_outside_the_window_are_ignored() {
+        // a grey middle with green edges that the crop leaves out
+        let mut frame = RgbaImage::from_pixel(16, 16, Rgba([0, 255, 0, 255]));
+        for y in 4..12 {
+            for x in 4..12 {
+                frame.put_pixel(x, y, Rgba([100, 100, 100, 255]));
+            }
+        }
+        let frame = Arc::new(frame);
+        let window = (4, 4, 8, 8);
+        let corrected = normalize_frames(
+            std::slice::from_ref(&frame),
+            &[window],
+            &settings(false, true, NormalizeMode::Independent),
+        );
+        assert_eq!(corrected[0].get_pixel(8, 8), &Rgba([100, 100, 100, 255]));
+    }
+}
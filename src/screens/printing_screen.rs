@@ -0,0 +1,251 @@
+mod sheet_renderer;
+
+use std::{process::Command, sync::Arc, time::Duration};
+
+use iced::{
+    widget::{container, image::Handle, text, Column, Space},
+    Element, Length,
+};
+use image::RgbaImage;
+
+use crate::{
+    config::{Config, OutputFormat},
+    utils::{
+        image_encoding::{encode_image, Attachment},
+        image_metadata::ImageMetadata,
+        loading_spinners,
+    },
+};
+
+use self::sheet_renderer::sheet_renderer;
+use super::delivery_screen::{continue_delivery, DeliveryPlan};
+
+/// Save `image` to a temporary file in the configured output format and run
+/// the configured print command on it. `{file}` and `{copies}` in the command
+/// are replaced with the path of the saved image and the number of copies to
+/// print.
+fn print_image(
+    image: &RgbaImage,
+    output_format: OutputFormat,
+    dpi: f32,
+    metadata: &ImageMetadata,
+    print_command: &[String],
+    copies: u32,
+) -> Result<(), String> {
+    let Some((program, args)) = print_command.split_first() else {
+        return Err("no print command is configured".to_string());
+    };
+    let path = std::env::temp_dir().join(format!(
+        "photo-booth-print-{}.{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_millis())
+            .unwrap_or_default(),
+        output_format.extension()
+    ));
+    std::fs::write(&path, encode_image(image, output_format, dpi, metadata)?)
+        .map_err(|e| format!("failed to save the image for printing: {}", e))?;
+    let status = Command::new(program)
+        .args(args.iter().map(|arg| {
+            arg.replace("{file}", &path.to_string_lossy())
+                .replace("{copies}", &copies.to_string())
+        }))
+        .status();
+    // the print command has spooled or printed the file by the time it exits
+    if let Err(e) = std::fs::remove_file(&path) {
+        eprintln!("warning: failed to remove the print file {:?}: {}", path, e);
+    }
+    let status = status.map_err(|e| format!("failed to run the print command: {}", e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("the print command exited with {}", status))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct PrintingScreen {
+    config: Config,
+    index: nokhwa::utils::CameraIndex,
+
+    preview_handle: Handle,
+    printable_image: Arc<RgbaImage>,
+    metadata: ImageMetadata,
+    attachments: Vec<Attachment>,
+    delivery_plan: DeliveryPlan,
+
+    started: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum PrintingScreenMessage {
+    StartPrint,
+    PrintFinished(Result<(), String>),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct PrintingScreenFlags {
+    pub config: Config,
+    pub index: nokhwa::utils::CameraIndex,
+
+    pub preview_handle: Handle,
+    pub printable_image: Arc<RgbaImage>,
+    pub metadata: ImageMetadata,
+    pub attachments: Vec<Attachment>,
+    pub delivery_plan: DeliveryPlan,
+}
+
+impl Into<super::ScreenMessage> for PrintingScreenMessage {
+    fn into(self) -> super::ScreenMessage {
+        super::ScreenMessage::PrintingScreenMessage(self)
+    }
+}
+
+impl super::Screenish for PrintingScreen {
+    type Message = PrintingScreenMessage;
+    type Flags = PrintingScreenFlags;
+
+    fn new(flags: PrintingScreenFlags) -> (Self, Option<PrintingScreenMessage>) {
+        (
+            PrintingScreen {
+                config: flags.config,
+                index: flags.index,
+
+                preview_handle: flags.preview_handle,
+                printable_image: flags.printable_image,
+                metadata: flags.metadata,
+                attachments: flags.attachments,
+                delivery_plan: flags.delivery_plan,
+
+                started: false,
+            },
+            Some(PrintingScreenMessage::StartPrint),
+        )
+    }
+
+    fn update(&mut self, message: PrintingScreenMessage) -> iced::Command<super::ScreenMessage> {
+        match message {
+            PrintingScreenMessage::StartPrint => {
+                if self.started {
+                    eprintln!("warning: PrintingScreenMessage::StartPrint called twice");
+                    return iced::Command::none();
+                }
+                self.started = true;
+                let image = self.printable_image.clone();
+                let print_command = self.config.print_command.clone();
+                let print_sheet = self.config.print_sheet.clone();
+                let copies = self.delivery_plan.option.print_copies;
+                let output_format = self.config.output_format;
+                let template_dpi = self.config.template.dpi;
+                let metadata = self.metadata.clone();
+                iced::Command::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || match print_sheet {
+                            Some(print_sheet) => print_image(
+                                &sheet_renderer(&image, &print_sheet),
+                                output_format,
+                                print_sheet.dpi,
+                                &metadata,
+                                &print_command,
+                                copies,
+                            ),
+                            None => print_image(
+                                &image,
+                                output_format,
+                                template_dpi,
+                                &metadata,
+                                &print_command,
+                                copies,
+                            ),
+                        })
+                        .await
+                        .unwrap_or_else(|_| Err("the print task panicked".to_string()))
+                    },
+                    PrintingScreenMessage::PrintFinished,
+                )
+                .map(super::ScreenMessage::PrintingScreenMessage)
+            }
+            PrintingScreenMessage::PrintFinished(result) => {
+                let flags = match result {
+                    Ok(()) => continue_delivery(
+                        self.config.clone(),
+                        self.index.clone(),
+                        self.preview_handle.clone(),
+                        self.printable_image.clone(),
+                        self.metadata.clone(),
+                        self.attachments.clone(),
+                        self.delivery_plan
+                            .clone()
+                            .done("Your photos are being printed. Grab them from the printer!"),
+                    ),
+                    // the other methods can still get the photos to the guest
+                    Err(reason) if self.delivery_plan.has_next() => {
+                        eprintln!("failed to print: {}", reason);
+                        continue_delivery(
+                            self.config.clone(),
+                            self.index.clone(),
+                            self.preview_handle.clone(),
+                            self.printable_image.clone(),
+                            self.metadata.clone(),
+                            self.attachments.clone(),
+                            self.delivery_plan.clone().failed(format!(
+                                "We couldn't print your photos, though. Error message: {}",
+                                reason
+                            )),
+                        )
+                    }
+                    Err(reason) => {
+                        eprintln!("failed to print: {}", reason);
+                        super::ScreenFlags::ErrorScreenFlags(
+                            super::error_screen::ErrorScreenFlags {
+                                config: self.config.clone(),
+                                index: self.index.clone(),
+
+                                error_title: "Something went wrong".to_string(),
+                                error_content: format!(
+                                    "We couldn't print your photos. Error message: {}",
+                                    reason
+                                ),
+                            },
+                        )
+                    }
+                };
+                iced::Command::perform(async {}, |_| {
+                    super::ScreenMessage::TransitionToScreen(flags)
+                })
+            }
+        }
+    }
+
+    fn view(&self) -> Element<PrintingScreenMessage> {
+        container(
+            Column::new()
+                .push(text("Printing your photos...").size(46))
+                .push(Space::with_height(24))
+                .push(
+                    loading_spinners::circular::Circular::new()
+                        .easing(&loading_spinners::easing::STANDARD)
+                        .cycle_duration(Duration::from_millis(2000))
+                        .size(86.0)
+                        .bar_height(8.0),
+                )
+                .align_items(iced::Alignment::Center)
+                .width(Length::Fill),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .align_y(iced::alignment::Vertical::Center)
+        .padding(20)
+        .into()
+    }
+
+    fn subscription(&self) -> iced::Subscription<PrintingScreenMessage> {
+        iced::Subscription::none()
+    }
+}
+
+impl Into<super::Screen> for PrintingScreen {
+    fn into(self) -> super::Screen {
+        super::Screen::PrintingScreen(self)
+    }
+}
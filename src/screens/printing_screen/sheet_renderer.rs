@@ -0,0 +1,194 @@
+use image::{imageops, Rgba, RgbaImage};
+
+use crate::config::PrintSheet;
+
+/// Length of the cut marks in inches.
+const CUT_MARK_LENGTH: f32 = 0.125;
+/// Thickness of the cut marks in inches.
+const CUT_MARK_THICKNESS: f32 = 0.01;
+
+struct Placement {
+    x: u32,
+    y: u32,
+}
+
+struct Layout {
+    rotate: bool,
+    width: u32,
+    height: u32,
+    placements: Vec<Placement>,
+}
+
+/// Split the sheet into `copies` cells, either side by side or stacked, and
+/// pick whichever arrangement (with or without rotating the strip) fits the
+/// strip at the largest size. `margin` is kept clear all around the sheet.
+fn layout(
+    sheet_width: u32,
+    sheet_height: u32,
+    strip_width: u32,
+    strip_height: u32,
+    copies: u32,
+    bleed: u32,
+    margin: u32,
+) -> Layout {
+    let area_width = sheet_width.saturating_sub(margin * 2);
+    let area_height = sheet_height.saturating_sub(margin * 2);
+    let mut best: Option<(f32, Layout)> = None;
+    for side_by_side in [true, false] {
+        for rotate in [false, true] {
+            let (width, height) = if rotate {
+                (strip_height as f32, strip_width as f32)
+            } else {
+                (strip_width as f32, strip_height as f32)
+            };
+            // every cell has bleed on both sides of the strip
+            let (cell_width, cell_height) = if side_by_side {
+                (area_width / copies, area_height)
+            } else {
+                (area_width, area_height / copies)
+            };
+            let available_width = cell_width.saturating_sub(bleed * 2) as f32;
+            let available_height = cell_height.saturating_sub(bleed * 2) as f32;
+            let scale = (available_width / width).min(available_height / height);
+            if best
+                .as_ref()
+                .is_some_and(|(best_scale, _)| *best_scale >= scale)
+            {
+                continue;
+            }
+            let width = (width * scale) as u32;
+            let height = (height * scale) as u32;
+            let placements = (0..copies)
+                .map(|i| {
+                    let (cell_x, cell_y) = if side_by_side {
+                        (margin + cell_width * i, margin)
+                    } else {
+                        (margin, margin + cell_height * i)
+                    };
+                    Placement {
+                        x: cell_x + (cell_width - width) / 2,
+                        y: cell_y + (cell_height - height) / 2,
+                    }
+                })
+                .collect();
+            best = Some((
+                scale,
+                Layout {
+                    rotate,
+                    width,
+                    height,
+                    placements,
+                },
+            ));
+        }
+    }
+    best.expect("there's always at least one layout").1
+}
+
+/// Extend the edge pixels of `strip` outwards by `bleed` pixels, so slightly
+/// inaccurate cuts don't leave a white edge.
+fn with_bleed(strip: RgbaImage, bleed: u32) -> RgbaImage {
+    if bleed == 0 {
+        return strip;
+    }
+    let (width, height) = strip.dimensions();
+    RgbaImage::from_fn(width + bleed * 2, height + bleed * 2, |x, y| {
+        *strip.get_pixel(
+            x.saturating_sub(bleed).min(width - 1),
+            y.saturating_sub(bleed).min(height - 1),
+        )
+    })
+}
+
+fn draw_rect(sheet: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32) {
+    let (sheet_width, sheet_height) = sheet.dimensions();
+    for y in y..(y + height).min(sheet_height) {
+        for x in x..(x + width).min(sheet_width) {
+            sheet.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+        }
+    }
+}
+
+/// Draw short lines on the edges of the sheet in line with every edge of the
+/// strips. They're `CUT_MARK_LENGTH` long, so they fit in the margin `layout`
+/// keeps for them.
+fn draw_cut_marks(sheet: &mut RgbaImage, layout: &Layout, dpi: f32) {
+    let (sheet_width, sheet_height) = sheet.dimensions();
+    let length = (CUT_MARK_LENGTH * dpi).ceil() as u32;
+    let thickness = ((CUT_MARK_THICKNESS * dpi).ceil() as u32).max(1);
+    for placement in &layout.placements {
+        for x in [placement.x, placement.x + layout.width] {
+            let x = x.saturating_sub(thickness / 2);
+            draw_rect(sheet, x, 0, thickness, length);
+            draw_rect(
+                sheet,
+                x,
+                sheet_height.saturating_sub(length),
+                thickness,
+                length,
+            );
+        }
+        for y in [placement.y, placement.y + layout.height] {
+            let y = y.saturating_sub(thickness / 2);
+            draw_rect(sheet, 0, y, length, thickness);
+            draw_rect(
+                sheet,
+                sheet_width.saturating_sub(length),
+                y,
+                length,
+                thickness,
+            );
+        }
+    }
+}
+
+/// Lay out `sheet.copies` copies of the strip on a white sheet of paper at the
+/// print resolution.
+pub(super) fn sheet_renderer(strip: &RgbaImage, sheet: &PrintSheet) -> RgbaImage {
+    let sheet_width = (sheet.paper_width * sheet.dpi).round() as u32;
+    let sheet_height = (sheet.paper_height * sheet.dpi).round() as u32;
+    let bleed = (sheet.bleed * sheet.dpi).round() as u32;
+    let cut_mark_length = (CUT_MARK_LENGTH * sheet.dpi).ceil() as u32;
+    let layout = layout(
+        sheet_width,
+        sheet_height,
+        strip.width(),
+        strip.height(),
+        sheet.copies.max(1),
+        bleed,
+        if sheet.cut_marks { cut_mark_length } else { 0 },
+    );
+
+    let mut rendered = RgbaImage::from_pixel(sheet_width, sheet_height, Rgba([255, 255, 255, 255]));
+    // the strips go on top, so the marks can never cover a photo
+    if sheet.cut_marks {
+        draw_cut_marks(&mut rendered, &layout, sheet.dpi);
+    }
+    if layout.width > 0 && layout.height > 0 {
+        let resized = if layout.rotate {
+            imageops::resize(
+                &imageops::rotate90(strip),
+                layout.width,
+                layout.height,
+                imageops::FilterType::Lanczos3,
+            )
+        } else {
+            imageops::resize(
+                strip,
+                layout.width,
+                layout.height,
+                imageops::FilterType::Lanczos3,
+            )
+        };
+        let strip = with_bleed(resized, bleed);
+        for placement in &layout.placements {
+            imageops::overlay(
+                &mut rendered,
+                &strip,
+                placement.x as i64 - bleed as i64,
+                placement.y as i64 - bleed as i64,
+            );
+        }
+    }
+    rendered
+}
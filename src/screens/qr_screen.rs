@@ -0,0 +1,286 @@
+use std::{sync::Arc, time::Duration};
+
+use base64::Engine;
+use iced::{
+    theme,
+    widget::{button, container, image::Handle, text, Column, Image, Space},
+    Element, Length,
+};
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    config::Config,
+    utils::{
+        container_styles::RoundedBoxContainerStyle,
+        image_encoding::{encode_image, Attachment},
+        image_metadata::ImageMetadata,
+        loading_spinners,
+    },
+};
+
+use super::delivery_screen::{continue_delivery, DeliveryPlan};
+
+/// Size of a single QR code module in pixels.
+const QR_MODULE_SIZE: u32 = 8;
+/// Width of the blank border around the QR code, in modules.
+const QR_QUIET_ZONE: u32 = 4;
+/// How long the QR code stays up before the booth moves on by itself, so a
+/// guest who walks away doesn't leave it stuck.
+const QR_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "status")]
+enum ServerResponse {
+    #[serde(rename = "error")]
+    Error { message: String },
+    #[serde(rename = "success")]
+    Success { url: String },
+}
+
+/// Render `url` as a black-on-white QR code.
+fn qr_code_handle(url: &str) -> Result<Handle, String> {
+    let code = qrcode::QrCode::new(url).map_err(|e| format!("failed to make QR code: {}", e))?;
+    let modules = code.width() as u32;
+    let size = (modules + QR_QUIET_ZONE * 2) * QR_MODULE_SIZE;
+    let colors = code.to_colors();
+    let image = RgbaImage::from_fn(size, size, |x, y| {
+        let module_x = (x / QR_MODULE_SIZE).checked_sub(QR_QUIET_ZONE);
+        let module_y = (y / QR_MODULE_SIZE).checked_sub(QR_QUIET_ZONE);
+        match (module_x, module_y) {
+            (Some(module_x), Some(module_y))
+                if module_x < modules
+                    && module_y < modules
+                    && colors[(module_y * modules + module_x) as usize] == qrcode::Color::Dark =>
+            {
+                Rgba([0, 0, 0, 255])
+            }
+            _ => Rgba([255, 255, 255, 255]),
+        }
+    });
+    Ok(Handle::from_pixels(
+        image.width(),
+        image.height(),
+        image.into_raw(),
+    ))
+}
+
+#[derive(Debug)]
+enum UploadState {
+    Uploading,
+    Uploaded(Handle),
+}
+
+#[derive(Debug)]
+pub(crate) struct QrScreen {
+    config: Config,
+    index: nokhwa::utils::CameraIndex,
+
+    preview_handle: Handle,
+    printable_image: Arc<RgbaImage>,
+    metadata: ImageMetadata,
+    attachments: Vec<Attachment>,
+    delivery_plan: DeliveryPlan,
+
+    upload_state: UploadState,
+}
+
+#[derive(Debug, Clone)]
+pub enum QrScreenMessage {
+    StartUpload,
+    UploadFinished(Result<Handle, String>),
+    DonePressed,
+    TimeoutFinished,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct QrScreenFlags {
+    pub config: Config,
+    pub index: nokhwa::utils::CameraIndex,
+
+    pub preview_handle: Handle,
+    pub printable_image: Arc<RgbaImage>,
+    pub metadata: ImageMetadata,
+    pub attachments: Vec<Attachment>,
+    pub delivery_plan: DeliveryPlan,
+}
+
+impl Into<super::ScreenMessage> for QrScreenMessage {
+    fn into(self) -> super::ScreenMessage {
+        super::ScreenMessage::QrScreenMessage(self)
+    }
+}
+
+impl super::Screenish for QrScreen {
+    type Message = QrScreenMessage;
+    type Flags = QrScreenFlags;
+
+    fn new(flags: QrScreenFlags) -> (Self, Option<QrScreenMessage>) {
+        (
+            QrScreen {
+                config: flags.config,
+                index: flags.index,
+
+                preview_handle: flags.preview_handle,
+                printable_image: flags.printable_image,
+                metadata: flags.metadata,
+                attachments: flags.attachments,
+                delivery_plan: flags.delivery_plan,
+
+                upload_state: UploadState::Uploading,
+            },
+            Some(QrScreenMessage::StartUpload),
+        )
+    }
+
+    fn update(&mut self, message: QrScreenMessage) -> iced::Command<super::ScreenMessage> {
+        match message {
+            QrScreenMessage::StartUpload => {
+                let endpoint = self.config.qr_server_endpoint.clone();
+                let image = self.printable_image.clone();
+                let output_format = self.config.output_format;
+                let dpi = self.config.template.dpi;
+                let metadata = self.metadata.clone();
+                let attachments: Vec<_> =
+                    self.attachments.iter().map(Attachment::to_json).collect();
+                iced::Command::perform(
+                    async move {
+                        let encoded =
+                            encode_image(&image, output_format, dpi, &metadata).map_err(|e| {
+                                eprintln!("{}", e);
+                                "failed to encode image for transport".to_string()
+                            })?;
+                        let base64_encoded =
+                            base64::engine::general_purpose::STANDARD.encode(encoded);
+                        let response = reqwest::Client::new()
+                            .post(endpoint)
+                            .body(
+                                json!({
+                                    "image": base64_encoded,
+                                    "imageMime": output_format.mime(),
+                                    "imageExtension": output_format.extension(),
+                                    "attachments": attachments
+                                })
+                                .to_string(),
+                            )
+                            .send()
+                            .await
+                            .map_err(|_| "The request didn't go through.".to_string())?;
+                        match response.json::<ServerResponse>().await {
+                            Ok(ServerResponse::Success { url }) => qr_code_handle(&url),
+                            Ok(ServerResponse::Error { message }) => Err(message),
+                            Err(e) => {
+                                eprintln!("failed to decode server response: {:?}", e);
+                                Err("We couldn't parse the response from the server.".to_string())
+                            }
+                        }
+                    },
+                    QrScreenMessage::UploadFinished,
+                )
+                .map(super::ScreenMessage::QrScreenMessage)
+            }
+            QrScreenMessage::UploadFinished(Ok(handle)) => {
+                self.upload_state = UploadState::Uploaded(handle);
+                iced::Command::none()
+            }
+            QrScreenMessage::UploadFinished(Err(reason)) => {
+                eprintln!("failed to upload for QR download: {}", reason);
+                let flags = super::error_screen::ErrorScreenFlags {
+                    config: self.config.clone(),
+                    index: self.index.clone(),
+
+                    error_title: "Something went wrong".to_string(),
+                    error_content: format!("Error message: {}", reason),
+                };
+                iced::Command::perform(async {}, |_| {
+                    super::ScreenMessage::TransitionToScreen(super::ScreenFlags::ErrorScreenFlags(
+                        flags,
+                    ))
+                })
+            }
+            QrScreenMessage::DonePressed | QrScreenMessage::TimeoutFinished => {
+                if matches!(self.upload_state, UploadState::Uploading) {
+                    return iced::Command::none();
+                }
+                let flags = continue_delivery(
+                    self.config.clone(),
+                    self.index.clone(),
+                    self.preview_handle.clone(),
+                    self.printable_image.clone(),
+                    self.metadata.clone(),
+                    self.attachments.clone(),
+                    self.delivery_plan.clone().done("Enjoy your photos!"),
+                );
+                iced::Command::perform(async {}, |_| {
+                    super::ScreenMessage::TransitionToScreen(flags)
+                })
+            }
+        }
+    }
+
+    fn view(&self) -> Element<QrScreenMessage> {
+        let content: Element<_> = match &self.upload_state {
+            UploadState::Uploading => Column::new()
+                .push(text("Uploading your photos...").size(46))
+                .push(Space::with_height(24))
+                .push(
+                    loading_spinners::circular::Circular::new()
+                        .easing(&loading_spinners::easing::STANDARD)
+                        .cycle_duration(Duration::from_millis(2000))
+                        .size(86.0)
+                        .bar_height(8.0),
+                )
+                .align_items(iced::Alignment::Center)
+                .into(),
+            UploadState::Uploaded(handle) => container(
+                Column::new()
+                    .push(text("Scan to download your photos").size(42))
+                    .push(Image::new(handle.clone()))
+                    .push(
+                        button(text("Press [Space] when you're done").size(24))
+                            .style(theme::Button::Primary)
+                            .on_press(QrScreenMessage::DonePressed),
+                    )
+                    .align_items(iced::Alignment::Center)
+                    .spacing(16),
+            )
+            .style(theme::Container::Custom(Box::new(
+                RoundedBoxContainerStyle {},
+            )))
+            .padding(24)
+            .into(),
+        };
+        container(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x()
+            .center_y()
+            .padding(20)
+            .into()
+    }
+
+    fn subscription(&self) -> iced::Subscription<QrScreenMessage> {
+        iced::Subscription::batch([
+            iced::keyboard::on_key_press(|key, _modifiers| match key {
+                iced::keyboard::Key::Named(iced::keyboard::key::Named::Space)
+                | iced::keyboard::Key::Named(iced::keyboard::key::Named::Enter) => {
+                    Some(QrScreenMessage::DonePressed)
+                }
+                _ => None,
+            }),
+            match self.upload_state {
+                UploadState::Uploading => iced::Subscription::none(),
+                UploadState::Uploaded(_) => {
+                    iced::time::every(QR_TIMEOUT).map(|_| QrScreenMessage::TimeoutFinished)
+                }
+            },
+        ])
+    }
+}
+
+impl Into<super::Screen> for QrScreen {
+    fn into(self) -> super::Screen {
+        super::Screen::QrScreen(self)
+    }
+}
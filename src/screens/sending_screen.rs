@@ -1,15 +1,24 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
 use base64::Engine;
 use iced::{
-    widget::{container, text, Column, Space},
+    widget::{container, image::Handle, text, Column, Space},
     Element, Length,
 };
-use image::{codecs::png::PngEncoder, ImageEncoder, RgbaImage};
+use image::RgbaImage;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
-use crate::{config::Config, utils::loading_spinners};
+use crate::{
+    config::Config,
+    utils::{
+        image_encoding::{encode_image, Attachment},
+        image_metadata::ImageMetadata,
+        loading_spinners,
+    },
+};
+
+use super::delivery_screen::{continue_delivery, DeliveryPlan};
 
 #[derive(Serialize, Deserialize)]
 #[serde(tag = "status")]
@@ -27,8 +36,14 @@ pub(crate) struct SendingScreen {
     config: Config,
     index: nokhwa::utils::CameraIndex,
 
-    image: Option<RgbaImage>,
+    preview_handle: Handle,
+    image: Option<Arc<RgbaImage>>,
+    metadata: ImageMetadata,
+    attachments: Vec<Attachment>,
     addresses: Vec<String>,
+    delivery_plan: DeliveryPlan,
+    /// Set once the request has gone out, so it's only ever sent once.
+    sending: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -51,8 +66,12 @@ pub(crate) struct SendingScreenFlags {
     pub config: Config,
     pub index: nokhwa::utils::CameraIndex,
 
-    pub image: RgbaImage,
+    pub preview_handle: Handle,
+    pub image: Arc<RgbaImage>,
+    pub metadata: ImageMetadata,
+    pub attachments: Vec<Attachment>,
     pub addresses: Vec<String>,
+    pub delivery_plan: DeliveryPlan,
 }
 
 impl Into<super::ScreenMessage> for SendingScreenMessage {
@@ -71,8 +90,13 @@ impl super::Screenish for SendingScreen {
                 config: flags.config,
                 index: flags.index,
 
+                preview_handle: flags.preview_handle,
                 image: Some(flags.image),
+                metadata: flags.metadata,
+                attachments: flags.attachments,
                 addresses: flags.addresses,
+                delivery_plan: flags.delivery_plan,
+                sending: false,
             },
             Some(SendingScreenMessage::StartSend),
         )
@@ -81,26 +105,33 @@ impl super::Screenish for SendingScreen {
     fn update(&mut self, message: SendingScreenMessage) -> iced::Command<super::ScreenMessage> {
         match message {
             SendingScreenMessage::StartSend => {
-                if let Some(image) = self.image.take() {
+                if self.sending {
+                    eprintln!(
+                        "warning: SendingScreenMessage::StartSend called while already sending"
+                    );
+                    return iced::Command::none();
+                }
+                if let Some(image) = self.image.clone() {
+                    self.sending = true;
                     let endpoint = self.config.email_server_endpoint.clone();
                     let recipients = self.addresses.clone();
+                    let output_format = self.config.output_format;
+                    let dpi = self.config.template.dpi;
+                    let metadata = self.metadata.clone();
+                    let attachments: Vec<_> =
+                        self.attachments.iter().map(Attachment::to_json).collect();
                     iced::Command::perform(
                         async move {
-                            // from one test, 2^18 seems to be the amount of memory needed
-                            let mut encoded: Vec<u8> = Vec::with_capacity(1 << 18);
-                            // if the encoder is switched, make sure to switch the mime
-                            let encoder = PngEncoder::new(&mut encoded);
-                            let encode_result = encoder.write_image(
-                                &image,
-                                image.width(),
-                                image.height(),
-                                image::ColorType::Rgba8,
-                            );
-                            if matches!(encode_result, Err(..)) {
-                                return SendResult::Failure(
-                                    "failed to encode image for transport".to_string(),
-                                );
-                            }
+                            let encoded = match encode_image(&image, output_format, dpi, &metadata)
+                            {
+                                Ok(encoded) => encoded,
+                                Err(e) => {
+                                    eprintln!("{}", e);
+                                    return SendResult::Failure(
+                                        "failed to encode image for transport".to_string(),
+                                    );
+                                }
+                            };
                             let base64_encoded =
                                 base64::engine::general_purpose::STANDARD.encode(encoded);
                             let response = reqwest::Client::new()
@@ -109,7 +140,9 @@ impl super::Screenish for SendingScreen {
                                     json!({
                                         "recipients": recipients,
                                         "image": base64_encoded,
-                                        "imageMime": "image/png" // make sure this matches up with the encoder used above
+                                        "imageMime": output_format.mime(),
+                                        "imageExtension": output_format.extension(),
+                                        "attachments": attachments
                                     })
                                     .to_string(),
                                 )
@@ -195,17 +228,18 @@ impl super::Screenish for SendingScreen {
                                 .to_string(),
                         },
                     ),
-                    SendResult::Success => super::ScreenFlags::AlertScreenFlags(
-                        super::alert_screen::AlertScreenFlags {
-                            config: self.config.clone(),
-                            index: self.index.clone(),
-
-                            alert_title: "All done!".to_string(),
-                            alert_content:
-                                "You should have received an email with your photos attached."
-                                    .to_string(),
-                            timeout: Duration::from_millis(4000),
-                        },
+                    SendResult::Success => continue_delivery(
+                        self.config.clone(),
+                        self.index.clone(),
+                        self.preview_handle.clone(),
+                        self.image
+                            .take()
+                            .expect("image is None when sending finished"),
+                        self.metadata.clone(),
+                        self.attachments.clone(),
+                        self.delivery_plan
+                            .clone()
+                            .done("You should have received an email with your photos attached."),
                     ),
                 };
                 iced::Command::perform(async {}, |_| {
@@ -1,4 +1,18 @@
+pub mod backgrounds;
+pub mod border_radius;
+pub mod camera_controls;
+pub mod chroma_key;
 pub mod circle;
+pub mod color_lut;
 pub mod container_styles;
+pub mod crop;
+pub mod crop_focus;
+pub mod digital_zoom;
+pub mod image_encoding;
+pub mod image_metadata;
 pub mod loading_spinners;
+pub mod looks;
+pub mod preview_renderer;
+pub mod sharpness;
 pub mod template_image;
+pub mod text_stamp;
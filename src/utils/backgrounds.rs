@@ -0,0 +1,33 @@
+use std::sync::Arc;
+
+use image::RgbaImage;
+
+use crate::config::Background;
+
+/// A background for the chroma key with its image loaded.
+#[derive(Debug, Clone)]
+pub struct LoadedBackground {
+    pub label: String,
+    pub image: Arc<RgbaImage>,
+}
+
+/// Load the backgrounds from the config. Backgrounds that can't be opened are
+/// left out.
+pub fn load_backgrounds(backgrounds: &[Background]) -> Vec<LoadedBackground> {
+    backgrounds
+        .iter()
+        .filter_map(|background| match image::open(&background.path) {
+            Ok(image) => Some(LoadedBackground {
+                label: background.label.clone(),
+                image: Arc::new(image.into_rgba8()),
+            }),
+            Err(e) => {
+                eprintln!(
+                    "warning: failed to open the background {:?}, it won't be offered: {}",
+                    background.path, e
+                );
+                None
+            }
+        })
+        .collect()
+}
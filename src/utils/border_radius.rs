@@ -1,6 +1,6 @@
 use image::{ImageBuffer, Rgba};
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct BorderRadius(pub u32, pub u32, pub u32, pub u32);
 
 impl From<u32> for BorderRadius {
@@ -10,7 +10,7 @@ impl From<u32> for BorderRadius {
 }
 
 /// See: https://users.rust-lang.org/t/how-to-trim-image-to-circle-image-without-jaggy/70374/2
-pub(super) fn round(img: &mut ImageBuffer<Rgba<u8>, Vec<u8>>, radius: &BorderRadius) {
+pub fn round(img: &mut ImageBuffer<Rgba<u8>, Vec<u8>>, radius: &BorderRadius) {
     let (width, height) = img.dimensions();
     assert!(radius.0 + radius.1 <= width);
     assert!(radius.3 + radius.2 <= width);
@@ -27,6 +27,73 @@ pub(super) fn round(img: &mut ImageBuffer<Rgba<u8>, Vec<u8>>, radius: &BorderRad
     border_radius(img, radius.3, |x, y| (x - 1, height - y));
 }
 
+/// The alpha of each rounded corner, worked out once so it can be applied to
+/// any number of images without drawing the curves every time.
+#[derive(Debug, Clone)]
+pub struct CornerMask {
+    radius: BorderRadius,
+    /// Top left, top right, bottom right and bottom left, each a square of
+    /// alpha values the size of its radius.
+    corners: [Vec<u8>; 4],
+}
+
+impl CornerMask {
+    pub fn new(radius: BorderRadius) -> Self {
+        let width = (radius.0 + radius.1).max(radius.3 + radius.2).max(1);
+        let height = (radius.0 + radius.3).max(radius.1 + radius.2).max(1);
+        let mut img = ImageBuffer::from_pixel(width, height, Rgba([255, 255, 255, 255]));
+        round(&mut img, &radius);
+        let corner = |r: u32, left: u32, top: u32| {
+            let mut alpha = Vec::with_capacity((r * r) as usize);
+            for y in top..top + r {
+                for x in left..left + r {
+                    alpha.push(img[(x, y)].0[3]);
+                }
+            }
+            alpha
+        };
+        CornerMask {
+            radius,
+            corners: [
+                corner(radius.0, 0, 0),
+                corner(radius.1, width - radius.1, 0),
+                corner(radius.2, width - radius.2, height - radius.2),
+                corner(radius.3, 0, height - radius.3),
+            ],
+        }
+    }
+
+    /// Round the corners of `img`. Images too small for the radius are left
+    /// alone.
+    pub fn apply(&self, img: &mut ImageBuffer<Rgba<u8>, Vec<u8>>) {
+        let (width, height) = img.dimensions();
+        let radius = self.radius;
+        if radius.0 + radius.1 > width
+            || radius.3 + radius.2 > width
+            || radius.0 + radius.3 > height
+            || radius.1 + radius.2 > height
+        {
+            return;
+        }
+        let origins = [
+            (0, 0),
+            (width - radius.1, 0),
+            (width - radius.2, height - radius.2),
+            (0, height - radius.3),
+        ];
+        let radii = [radius.0, radius.1, radius.2, radius.3];
+        for ((alpha, r), (left, top)) in self.corners.iter().zip(radii).zip(origins) {
+            for y in 0..r {
+                for x in 0..r {
+                    let mask = alpha[(y * r + x) as usize] as u16;
+                    let pixel_alpha = &mut img[(left + x, top + y)].0[3];
+                    *pixel_alpha = ((mask * *pixel_alpha as u16 + 127) / 255) as u8;
+                }
+            }
+        }
+    }
+}
+
 fn border_radius(
     img: &mut ImageBuffer<Rgba<u8>, Vec<u8>>,
     r: u32,
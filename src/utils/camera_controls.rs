@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+
+use nokhwa::{
+    utils::{ControlValueDescription, ControlValueSetter, KnownCameraControl},
+    Camera,
+};
+
+use crate::config::{CameraControlSetting, CameraControlValue, Config};
+
+/// The names controls are saved under. Controls that only some cameras have
+/// are saved under their number instead.
+const CONTROL_KEYS: [(KnownCameraControl, &str); 15] = [
+    (KnownCameraControl::Brightness, "brightness"),
+    (KnownCameraControl::Contrast, "contrast"),
+    (KnownCameraControl::Hue, "hue"),
+    (KnownCameraControl::Saturation, "saturation"),
+    (KnownCameraControl::Sharpness, "sharpness"),
+    (KnownCameraControl::Gamma, "gamma"),
+    (KnownCameraControl::WhiteBalance, "whiteBalance"),
+    (KnownCameraControl::BacklightComp, "backlightComp"),
+    (KnownCameraControl::Gain, "gain"),
+    (KnownCameraControl::Pan, "pan"),
+    (KnownCameraControl::Tilt, "tilt"),
+    (KnownCameraControl::Zoom, "zoom"),
+    (KnownCameraControl::Exposure, "exposure"),
+    (KnownCameraControl::Iris, "iris"),
+    (KnownCameraControl::Focus, "focus"),
+];
+
+/// The name `control` is saved under.
+pub fn control_key(control: KnownCameraControl) -> String {
+    match control {
+        KnownCameraControl::Other(id) => id.to_string(),
+        known => CONTROL_KEYS
+            .iter()
+            .find(|(control, _)| *control == known)
+            .map(|(_, key)| key.to_string())
+            .unwrap_or_else(|| format!("{:?}", known)),
+    }
+}
+
+fn known_control(key: &str) -> Option<KnownCameraControl> {
+    CONTROL_KEYS
+        .iter()
+        .find(|(_, control_key)| *control_key == key)
+        .map(|(control, _)| *control)
+        .or_else(|| key.parse().ok().map(KnownCameraControl::Other))
+}
+
+/// The current value of a control, if it's a kind that can be saved.
+pub fn control_value(description: &ControlValueDescription) -> Option<CameraControlValue> {
+    match description {
+        ControlValueDescription::Integer { value, .. }
+        | ControlValueDescription::IntegerRange { value, .. }
+        | ControlValueDescription::Enum { value, .. } => Some(CameraControlValue::Integer(*value)),
+        ControlValueDescription::Float { value, .. }
+        | ControlValueDescription::FloatRange { value, .. } => {
+            Some(CameraControlValue::Float(*value))
+        }
+        ControlValueDescription::Boolean { value, .. } => Some(CameraControlValue::Boolean(*value)),
+        _ => None,
+    }
+}
+
+/// The value the camera starts a control at, if it's a kind that can be saved.
+pub fn default_control_value(description: &ControlValueDescription) -> Option<CameraControlValue> {
+    match description {
+        ControlValueDescription::Integer { default, .. }
+        | ControlValueDescription::IntegerRange { default, .. }
+        | ControlValueDescription::Enum { default, .. } => {
+            Some(CameraControlValue::Integer(*default))
+        }
+        ControlValueDescription::Float { default, .. }
+        | ControlValueDescription::FloatRange { default, .. } => {
+            Some(CameraControlValue::Float(*default))
+        }
+        ControlValueDescription::Boolean { default, .. } => {
+            Some(CameraControlValue::Boolean(*default))
+        }
+        _ => None,
+    }
+}
+
+/// Change the current value in `description` to `value`, if it's the right
+/// kind.
+pub fn set_control_value(description: &mut ControlValueDescription, new_value: CameraControlValue) {
+    match (description, new_value) {
+        (
+            ControlValueDescription::Integer { value, .. }
+            | ControlValueDescription::IntegerRange { value, .. }
+            | ControlValueDescription::Enum { value, .. },
+            CameraControlValue::Integer(new_value),
+        ) => *value = new_value,
+        (
+            ControlValueDescription::Float { value, .. }
+            | ControlValueDescription::FloatRange { value, .. },
+            CameraControlValue::Float(new_value),
+        ) => *value = new_value,
+        (
+            ControlValueDescription::Boolean { value, .. },
+            CameraControlValue::Boolean(new_value),
+        ) => *value = new_value,
+        _ => {}
+    }
+}
+
+/// How to set a control described by `description` to `value`, or `None` if
+/// the value doesn't suit it.
+pub fn control_setter(
+    description: &ControlValueDescription,
+    value: CameraControlValue,
+) -> Option<ControlValueSetter> {
+    Some(match (description, value) {
+        (ControlValueDescription::Enum { .. }, CameraControlValue::Integer(value)) => {
+            ControlValueSetter::EnumValue(value)
+        }
+        (
+            ControlValueDescription::Integer { .. } | ControlValueDescription::IntegerRange { .. },
+            CameraControlValue::Integer(value),
+        ) => ControlValueSetter::Integer(value),
+        (
+            ControlValueDescription::Integer { .. } | ControlValueDescription::IntegerRange { .. },
+            CameraControlValue::Float(value),
+        ) => ControlValueSetter::Integer(value.round() as i64),
+        (
+            ControlValueDescription::Float { .. } | ControlValueDescription::FloatRange { .. },
+            CameraControlValue::Float(value),
+        ) => ControlValueSetter::Float(value),
+        (
+            ControlValueDescription::Float { .. } | ControlValueDescription::FloatRange { .. },
+            CameraControlValue::Integer(value),
+        ) => ControlValueSetter::Float(value as f64),
+        (ControlValueDescription::Boolean { .. }, CameraControlValue::Boolean(value)) => {
+            ControlValueSetter::Boolean(value)
+        }
+        _ => return None,
+    })
+}
+
+/// Set the controls in `settings` on `camera`. Controls the camera doesn't
+/// have and values that don't suit them are skipped.
+pub fn apply_camera_controls(camera: &mut Camera, settings: &[CameraControlSetting]) {
+    if settings.is_empty() {
+        return;
+    }
+    let controls = match camera.camera_controls() {
+        Ok(controls) => controls,
+        Err(e) => {
+            eprintln!(
+                "warning: failed to read the camera's controls, none were set: {}",
+                e
+            );
+            return;
+        }
+    };
+    for setting in settings {
+        let Some(control) = known_control(&setting.control)
+            .and_then(|known| controls.iter().find(|control| control.control() == known))
+        else {
+            eprintln!("warning: the camera has no {:?} control", setting.control);
+            continue;
+        };
+        let Some(setter) = control_setter(control.description(), setting.value) else {
+            eprintln!(
+                "warning: {:?} isn't a value for the {:?} control",
+                setting.value, setting.control
+            );
+            continue;
+        };
+        if let Err(e) = camera.set_camera_control(control.control(), setter) {
+            eprintln!(
+                "warning: failed to set the {:?} control: {}",
+                setting.control, e
+            );
+        }
+    }
+}
+
+fn read_saved_camera_controls(
+    path: &str,
+) -> Result<HashMap<String, Vec<CameraControlSetting>>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(source) => serde_json::from_str(&source).map_err(|e| e.to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Replace the camera controls in `config` with the ones saved from the
+/// camera settings, if there are any.
+pub fn load_saved_camera_controls(config: &mut Config) {
+    match read_saved_camera_controls(&config.camera_controls_file) {
+        Ok(saved) => config.camera_controls.extend(saved),
+        Err(e) => eprintln!(
+            "warning: failed to read the saved camera controls {:?}: {}",
+            config.camera_controls_file, e
+        ),
+    }
+}
+
+/// Save `settings` as the locked controls of the camera called `camera`,
+/// keeping the ones saved for other cameras.
+pub fn save_camera_controls(
+    path: &str,
+    camera: &str,
+    settings: Vec<CameraControlSetting>,
+) -> Result<(), String> {
+    let mut saved = read_saved_camera_controls(path)?;
+    saved.insert(camera.to_string(), settings);
+    let source = serde_json::to_string_pretty(&saved).map_err(|e| e.to_string())?;
+    std::fs::write(path, source).map_err(|e| e.to_string())
+}
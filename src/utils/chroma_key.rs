@@ -0,0 +1,111 @@
+use image::{imageops, RgbaImage};
+
+use super::crop::crop_rect;
+
+/// Luma of an RGB colour with channels from 0 to 1.
+fn luma([r, g, b]: [f32; 3]) -> f32 {
+    0.299 * r + 0.587 * g + 0.114 * b
+}
+
+/// The blue and red difference of an RGB colour, which is where the
+/// colour is without its brightness.
+fn chroma(color: [f32; 3], luma: f32) -> (f32, f32) {
+    ((color[2] - luma) * 0.564, (color[0] - luma) * 0.713)
+}
+
+/// Separates guests from a coloured backdrop and puts a new background
+/// behind them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChromaKeyer {
+    /// The chroma of the key colour.
+    key: (f32, f32),
+    /// How saturated the key colour is, which the other settings are
+    /// relative to.
+    key_length: f32,
+    tolerance: f32,
+    softness: f32,
+    spill_suppression: f32,
+}
+
+impl ChromaKeyer {
+    /// `tolerance` is how far from the key colour a colour can be and still
+    /// count as backdrop, where 0 is only the key colour and 1 is grey.
+    /// Colours up to `softness` further away are blended. `spill_suppression`
+    /// is how much of the key colour reflected onto the guests is taken out.
+    pub fn new(key_color: [u8; 3], tolerance: f32, softness: f32, spill_suppression: f32) -> Self {
+        let key_color = key_color.map(|value| value as f32 / 255.0);
+        let key = chroma(key_color, luma(key_color));
+        ChromaKeyer {
+            key,
+            // a grey key colour would key out everything grey
+            key_length: (key.0 * key.0 + key.1 * key.1).sqrt().max(0.01),
+            tolerance: tolerance.max(0.0),
+            softness: softness.max(0.0),
+            spill_suppression: spill_suppression.clamp(0.0, 1.0),
+        }
+    }
+
+    /// How much of `color` is kept, from 0 to 1, and the colour with the
+    /// spill taken out.
+    fn key_color(&self, color: [f32; 3]) -> (f32, [f32; 3]) {
+        let luma = luma(color);
+        let (blue, red) = chroma(color, luma);
+        let (key_blue, key_red) = self.key;
+        let distance =
+            ((blue - key_blue).powi(2) + (red - key_red).powi(2)).sqrt() / self.key_length;
+        let kept = if distance <= self.tolerance {
+            0.0
+        } else if distance >= self.tolerance + self.softness {
+            1.0
+        } else {
+            let t = (distance - self.tolerance) / self.softness;
+            t * t * (3.0 - 2.0 * t)
+        };
+
+        // take out the part of the chroma that points towards the key colour,
+        // keeping the brightness
+        let towards_key = (blue * key_blue + red * key_red) / (self.key_length * self.key_length);
+        if towards_key <= 0.0 || self.spill_suppression == 0.0 {
+            return (kept, color);
+        }
+        let removed = towards_key * self.spill_suppression;
+        let (blue, red) = (blue - key_blue * removed, red - key_red * removed);
+        let r = luma + red / 0.713;
+        let b = luma + blue / 0.564;
+        let g = (luma - 0.299 * r - 0.114 * b) / 0.587;
+        (kept, [r, g, b])
+    }
+
+    /// Replace the backdrop in `frame` with `background`, which has to be the
+    /// same size. Alpha is left alone.
+    pub fn composite(&self, frame: &mut RgbaImage, background: &RgbaImage) {
+        debug_assert_eq!(frame.dimensions(), background.dimensions());
+        for (pixel, background) in frame.chunks_exact_mut(4).zip(background.chunks_exact(4)) {
+            let color = [pixel[0], pixel[1], pixel[2]].map(|value| value as f32 / 255.0);
+            let (kept, color) = self.key_color(color);
+            for channel in 0..3 {
+                let background = background[channel] as f32 / 255.0;
+                let value = background + (color[channel] - background) * kept;
+                pixel[channel] = (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+            }
+        }
+    }
+}
+
+/// Scale and crop `background` to fill `width` by `height`, keeping it
+/// centred.
+pub fn cover(
+    background: &RgbaImage,
+    width: u32,
+    height: u32,
+    filter: imageops::FilterType,
+) -> RgbaImage {
+    let (left, top, crop_width, crop_height) = crop_rect(
+        background.width(),
+        background.height(),
+        Some(width as f32 / height as f32),
+        (0.5, 0.5),
+    );
+    let cropped = imageops::crop_imm(background, left, top, crop_width, crop_height);
+    imageops::resize(&*cropped, width, height, filter)
+}
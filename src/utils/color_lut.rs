@@ -0,0 +1,169 @@
+use image::RgbaImage;
+
+/// A 3D colour lookup table, mapping every RGB colour to a new one. Colours
+/// between the points of the table are interpolated.
+#[derive(Clone, PartialEq)]
+pub struct Lut3d {
+    size: usize,
+    /// Output colours from 0 to 1, with red changing fastest, then green,
+    /// then blue, like in `.cube` files.
+    table: Vec<[f32; 3]>,
+    /// The input colours at the first and last points of the table.
+    domain: ([f32; 3], [f32; 3]),
+}
+
+impl std::fmt::Debug for Lut3d {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Lut3d").field("size", &self.size).finish()
+    }
+}
+
+impl Lut3d {
+    /// Sample `f` at `size` points along each axis. Colours go in and out as
+    /// `[r, g, b]` from 0 to 1.
+    pub fn from_fn(size: usize, f: impl Fn([f32; 3]) -> [f32; 3]) -> Self {
+        let size = size.max(2);
+        let step = 1.0 / (size - 1) as f32;
+        let mut table = Vec::with_capacity(size * size * size);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    table.push(f([r as f32 * step, g as f32 * step, b as f32 * step]));
+                }
+            }
+        }
+        Lut3d {
+            size,
+            table,
+            domain: ([0.0; 3], [1.0; 3]),
+        }
+    }
+
+    /// Parse a 3D LUT in the `.cube` format from Adobe and Resolve.
+    pub fn parse_cube(source: &str) -> Result<Self, String> {
+        let mut size = None;
+        let mut domain_min = [0.0f32; 3];
+        let mut domain_max = [1.0f32; 3];
+        let mut table = Vec::new();
+        let parse = |word: &str, line: usize| {
+            word.parse::<f32>()
+                .map_err(|_| format!("line {}: {:?} isn't a number", line, word))
+        };
+        let parse_triple = |words: &[&str], line: usize| -> Result<[f32; 3], String> {
+            match words {
+                [r, g, b] => Ok([parse(r, line)?, parse(g, line)?, parse(b, line)?]),
+                _ => Err(format!("line {}: expected three numbers", line)),
+            }
+        };
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words[0] {
+                "TITLE" => {}
+                "LUT_3D_SIZE" => {
+                    size = Some(
+                        words
+                            .get(1)
+                            .and_then(|size| size.parse::<usize>().ok())
+                            .filter(|size| *size >= 2)
+                            .ok_or_else(|| format!("line {}: bad LUT_3D_SIZE", line_number))?,
+                    );
+                }
+                "DOMAIN_MIN" => domain_min = parse_triple(&words[1..], line_number)?,
+                "DOMAIN_MAX" => domain_max = parse_triple(&words[1..], line_number)?,
+                // the older Resolve way of giving the domain, the same for
+                // every channel
+                "LUT_3D_INPUT_RANGE" => match &words[1..] {
+                    [min, max] => {
+                        domain_min = [parse(min, line_number)?; 3];
+                        domain_max = [parse(max, line_number)?; 3];
+                    }
+                    _ => return Err(format!("line {}: expected two numbers", line_number)),
+                },
+                // only shapes the input of a 1D table, which isn't supported
+                "LUT_1D_INPUT_RANGE" => {}
+                "LUT_1D_SIZE" => return Err("1D LUTs aren't supported".to_string()),
+                _ => table.push(parse_triple(&words, line_number)?),
+            }
+        }
+        let size = size.ok_or("the LUT_3D_SIZE is missing")?;
+        if table.len() != size * size * size {
+            return Err(format!(
+                "expected {} entries for a size of {} but found {}",
+                size * size * size,
+                size,
+                table.len()
+            ));
+        }
+        if (0..3).any(|channel| domain_max[channel] <= domain_min[channel]) {
+            return Err("DOMAIN_MAX has to be above DOMAIN_MIN".to_string());
+        }
+        Ok(Lut3d {
+            size,
+            table,
+            domain: (domain_min, domain_max),
+        })
+    }
+
+    /// Map every pixel of `image` through the table, leaving alpha alone.
+    pub fn apply(&self, image: &mut RgbaImage) {
+        // for each 8-bit value of each channel, the offset into the table of
+        // the point below it and how far it is towards the next point, worked
+        // out once so each pixel only has to look them up
+        let (domain_min, domain_max) = self.domain;
+        let strides = [1, self.size, self.size * self.size];
+        let positions: Vec<[(usize, f32); 256]> = (0..3)
+            .map(|channel| {
+                let mut positions = [(0, 0.0); 256];
+                for (value, position) in positions.iter_mut().enumerate() {
+                    let input = (value as f32 / 255.0 - domain_min[channel])
+                        / (domain_max[channel] - domain_min[channel]);
+                    let point = input.clamp(0.0, 1.0) * (self.size - 1) as f32;
+                    let low = (point as usize).min(self.size - 2);
+                    *position = (low * strides[channel], point - low as f32);
+                }
+                positions
+            })
+            .collect();
+        for pixel in image.chunks_exact_mut(4) {
+            let red = positions[0][pixel[0] as usize];
+            let green = positions[1][pixel[1] as usize];
+            let blue = positions[2][pixel[2] as usize];
+            // tetrahedral interpolation: walk from the lowest corner of the
+            // cube around the colour to the highest, along the channel with
+            // the most left over first, so only four points are looked at
+            let mut axes = [
+                (strides[0], red.1),
+                (strides[1], green.1),
+                (strides[2], blue.1),
+            ];
+            if axes[0].1 < axes[1].1 {
+                axes.swap(0, 1);
+            }
+            if axes[1].1 < axes[2].1 {
+                axes.swap(1, 2);
+            }
+            if axes[0].1 < axes[1].1 {
+                axes.swap(0, 1);
+            }
+            let mut index = red.0 + green.0 + blue.0;
+            let mut point = self.table[index];
+            let mut color = point;
+            for (stride, fraction) in axes {
+                index += stride;
+                let next = self.table[index];
+                for channel in 0..3 {
+                    color[channel] += (next[channel] - point[channel]) * fraction;
+                }
+                point = next;
+            }
+            for (channel, value) in pixel[..3].iter_mut().zip(color) {
+                *channel = (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+            }
+        }
+    }
+}
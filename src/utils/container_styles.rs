@@ -61,3 +61,22 @@ impl StyleSheet for RoundedErrorBoxContainerStyle {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NextSlotContainerStyle {}
+
+impl StyleSheet for NextSlotContainerStyle {
+    type Style = iced::Theme;
+
+    fn appearance(&self, style: &Self::Style) -> iced::widget::container::Appearance {
+        let palette = style.extended_palette();
+        iced::widget::container::Appearance {
+            border: Border {
+                radius: Radius::from(0),
+                width: 4.0,
+                color: palette.primary.strong.color,
+            },
+            ..Default::default()
+        }
+    }
+}
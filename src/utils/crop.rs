@@ -0,0 +1,60 @@
+/// Where a window of `window` pixels out of `total` starts to be centred on
+/// `centre`, a fraction of `total`, without leaving it.
+fn window_offset(total: u32, window: u32, centre: f32) -> u32 {
+    let start = centre.clamp(0.0, 1.0) * total as f32 - window as f32 / 2.0;
+    (start.round().max(0.0) as u32).min(total - window)
+}
+
+/// The part of a `width` by `height` frame that's kept when cropping it to
+/// `aspect_ratio`, as `(left, top, width, height)`. The crop is centred on
+/// `focus`, given as fractions of the frame's width and height, as far as it
+/// can be without leaving the frame.
+pub fn crop_rect(
+    width: u32,
+    height: u32,
+    aspect_ratio: Option<f32>,
+    focus: (f32, f32),
+) -> (u32, u32, u32, u32) {
+    let Some(aspect_ratio) = aspect_ratio else {
+        return (0, 0, width, height);
+    };
+    let frame_aspect_ratio = width as f32 / height as f32;
+    if aspect_ratio < frame_aspect_ratio {
+        // trim off left and right
+        let new_width = ((height as f32 * aspect_ratio) as u32).clamp(1, width);
+        (
+            window_offset(width, new_width, focus.0),
+            0,
+            new_width,
+            height,
+        )
+    } else if aspect_ratio > frame_aspect_ratio {
+        // trim off top and bottom
+        let new_height = ((width as f32 / aspect_ratio) as u32).clamp(1, height);
+        (
+            0,
+            window_offset(height, new_height, focus.1),
+            width,
+            new_height,
+        )
+    } else {
+        // perfect aspect ratio!
+        (0, 0, width, height)
+    }
+}
+
+/// The part of a `width` by `height` frame that's kept when zooming in by
+/// `zoom`, as `(left, top, width, height)`. It's centred on `centre`, given as
+/// fractions of the frame's width and height, as far as it can be without
+/// leaving the frame.
+pub fn zoom_rect(width: u32, height: u32, zoom: f32, centre: (f32, f32)) -> (u32, u32, u32, u32) {
+    let zoom = zoom.max(1.0);
+    let zoomed_width = ((width as f32 / zoom).round() as u32).clamp(1, width);
+    let zoomed_height = ((height as f32 / zoom).round() as u32).clamp(1, height);
+    (
+        window_offset(width, zoomed_width, centre.0),
+        window_offset(height, zoomed_height, centre.1),
+        zoomed_width,
+        zoomed_height,
+    )
+}
@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use image::{imageops, RgbaImage};
+
+use crate::config::CropStrategy;
+
+/// Faces are looked for in a copy of the photo scaled down to this width,
+/// which is plenty to find guests standing in front of the booth.
+const FACE_DETECTION_WIDTH: u32 = 480;
+
+fn read_face_model(path: &str) -> Result<rustface::Model, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    rustface::read_model(bytes.as_slice()).map_err(|e| e.to_string())
+}
+
+/// Works out where to centre crops of a photo for a [`CropStrategy`].
+#[derive(Clone)]
+pub struct CropFocus {
+    strategy: CropStrategy,
+    /// The face model, parsed once. Detectors aren't thread-safe, so one is
+    /// made from a copy of it for every photo.
+    face_model: Option<Arc<rustface::Model>>,
+}
+
+impl std::fmt::Debug for CropFocus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CropFocus")
+            .field("strategy", &self.strategy)
+            .finish()
+    }
+}
+
+impl CropFocus {
+    pub fn new(strategy: &CropStrategy) -> Self {
+        let face_model = match strategy {
+            CropStrategy::Faces { model } => match read_face_model(model) {
+                Ok(model) => Some(Arc::new(model)),
+                Err(e) => {
+                    eprintln!(
+                        "warning: failed to load the face model {}, photos will be centred: {}",
+                        model, e
+                    );
+                    None
+                }
+            },
+            _ => None,
+        };
+        CropFocus {
+            strategy: strategy.clone(),
+            face_model,
+        }
+    }
+
+    /// Whether the focus depends on what's in the photo, so it's worth
+    /// working out again as the photo changes.
+    pub fn is_dynamic(&self) -> bool {
+        self.face_model.is_some()
+    }
+
+    /// Where crops of `frame` should be centred, as fractions of its width
+    /// and height.
+    pub fn focus(&self, frame: &RgbaImage) -> (f32, f32) {
+        match &self.strategy {
+            CropStrategy::Centre => (0.5, 0.5),
+            CropStrategy::FocalPoint { x, y } => (*x, *y),
+            CropStrategy::Faces { .. } => self.faces_focus(frame).unwrap_or((0.5, 0.5)),
+        }
+    }
+
+    /// Like [`CropFocus::focus`], for a `frame` that was mirrored after it
+    /// came off the camera, so a focal point still picks the same side of
+    /// the scene.
+    pub fn mirrored_focus(&self, frame: &RgbaImage) -> (f32, f32) {
+        match &self.strategy {
+            CropStrategy::FocalPoint { x, y } => (1.0 - *x, *y),
+            _ => self.focus(frame),
+        }
+    }
+
+    /// The centre of the box around every face in `frame`.
+    fn faces_focus(&self, frame: &RgbaImage) -> Option<(f32, f32)> {
+        let model = self.face_model.as_ref()?;
+        let mut detector = rustface::create_detector_with_model(model.as_ref().clone());
+        detector.set_min_face_size(20);
+        detector.set_score_thresh(2.0);
+        detector.set_pyramid_scale_factor(0.8);
+        detector.set_slide_window_step(4, 4);
+
+        let scale = (FACE_DETECTION_WIDTH as f32 / frame.width() as f32).min(1.0);
+        let width = ((frame.width() as f32 * scale) as u32).max(1);
+        let height = ((frame.height() as f32 * scale) as u32).max(1);
+        let gray = imageops::grayscale(&imageops::thumbnail(frame, width, height));
+        let faces = detector.detect(&mut rustface::ImageData::new(gray.as_raw(), width, height));
+        if faces.is_empty() {
+            return None;
+        }
+
+        let (left, top, right, bottom) = faces.iter().map(|face| face.bbox()).fold(
+            (i32::MAX, i32::MAX, i32::MIN, i32::MIN),
+            |(left, top, right, bottom), bbox| {
+                (
+                    left.min(bbox.x()),
+                    top.min(bbox.y()),
+                    right.max(bbox.x() + bbox.width() as i32),
+                    bottom.max(bbox.y() + bbox.height() as i32),
+                )
+            },
+        );
+        Some((
+            (left + right) as f32 / 2.0 / width as f32,
+            (top + bottom) as f32 / 2.0 / height as f32,
+        ))
+    }
+}
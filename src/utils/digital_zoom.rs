@@ -0,0 +1,24 @@
+use crate::config::{Config, DigitalZoom};
+
+/// Replace the digital zoom in `config` with the one saved from admin mode,
+/// if there is one.
+pub fn load_saved_digital_zoom(config: &mut Config) {
+    let saved = match std::fs::read_to_string(&config.digital_zoom_file) {
+        Ok(source) => serde_json::from_str(&source).map_err(|e| e.to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+        Err(e) => Err(e.to_string()),
+    };
+    match saved {
+        Ok(saved) => config.digital_zoom = saved,
+        Err(e) => eprintln!(
+            "warning: failed to read the saved digital zoom {:?}: {}",
+            config.digital_zoom_file, e
+        ),
+    }
+}
+
+/// Save `digital_zoom` so the booth starts with it next time.
+pub fn save_digital_zoom(path: &str, digital_zoom: DigitalZoom) -> Result<(), String> {
+    let source = serde_json::to_string_pretty(&digital_zoom).map_err(|e| e.to_string())?;
+    std::fs::write(path, source).map_err(|e| e.to_string())
+}
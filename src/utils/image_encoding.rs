@@ -0,0 +1,528 @@
+use std::sync::Arc;
+
+use base64::Engine;
+use image::{
+    codecs::{
+        gif::{GifEncoder, Repeat},
+        jpeg::JpegEncoder,
+        webp::WebPEncoder,
+    },
+    ColorType, Delay, Frame, ImageEncoder, RgbImage, RgbaImage,
+};
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, TextStr};
+use serde_json::json;
+
+use crate::config::{AnimatedLoopFormat, OutputFormat};
+
+use super::image_metadata::ImageMetadata;
+
+/// Points per inch in PDF user space.
+const PDF_POINTS_PER_INCH: f32 = 72.0;
+/// Marker that starts a JPEG file.
+const JPEG_SOI: [u8; 2] = [0xff, 0xd8];
+/// Marker of the JFIF segment the JPEG encoder writes first.
+const JPEG_APP0: [u8; 2] = [0xff, 0xe0];
+/// Marker of the segment holding EXIF data.
+const JPEG_APP1: [u8; 2] = [0xff, 0xe1];
+/// Flags in a WebP VP8X chunk.
+const WEBP_FLAG_ANIMATION: u8 = 0x02;
+const WEBP_FLAG_EXIF: u8 = 0x08;
+const WEBP_FLAG_ALPHA: u8 = 0x10;
+/// Flag in a WebP ANMF chunk to draw the frame over the canvas as-is.
+const WEBP_FRAME_NO_BLEND: u8 = 0x02;
+/// Frame durations are stored in 24 bits.
+const WEBP_MAX_FRAME_DURATION: u32 = (1 << 24) - 1;
+/// NeuQuant speed for GIF palettes, from 1 (best) to 30 (fastest). The
+/// default of 1 takes seconds per photo.
+const GIF_QUANTIZATION_SPEED: i32 = 10;
+
+/// A file sent along with the strip, already encoded.
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub filename: String,
+    pub mime: &'static str,
+    /// Shared, since attachments are passed along from screen to screen.
+    pub data: Arc<[u8]>,
+}
+
+impl Attachment {
+    /// The attachment as sent to the callback services.
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "filename": self.filename,
+            "image": base64::engine::general_purpose::STANDARD.encode(&self.data),
+            "imageMime": self.mime
+        })
+    }
+}
+
+/// Put `image` on a white background, since JPEG has no alpha channel.
+fn flatten(image: &RgbaImage) -> RgbImage {
+    RgbImage::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        let blend =
+            |channel: u8| ((channel as u16 * a as u16 + 255 * (255 - a as u16) + 127) / 255) as u8;
+        image::Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+fn encode_png(image: &RgbaImage, metadata: &ImageMetadata) -> Result<Vec<u8>, png::EncodingError> {
+    // from one test, 2^18 seems to be the amount of memory needed
+    let mut encoded = Vec::with_capacity(1 << 18);
+    let mut encoder = png::Encoder::new(&mut encoded, image.width(), image.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    // same as the image crate's PNG encoder
+    encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive);
+    for (keyword, text) in metadata.png_text() {
+        encoder.add_itxt_chunk(keyword, text)?;
+    }
+    let mut writer = encoder.write_header()?;
+    writer.write_chunk(png::chunk::ChunkType(*b"eXIf"), &metadata.exif())?;
+    writer.write_image_data(image)?;
+    writer.finish()?;
+    Ok(encoded)
+}
+
+/// Encode a JPEG with an EXIF segment after the JFIF one.
+fn encode_jpeg(
+    image: &RgbaImage,
+    quality: u8,
+    metadata: &ImageMetadata,
+) -> Result<Vec<u8>, String> {
+    let flattened = flatten(image);
+    let mut encoded = Vec::with_capacity(1 << 18);
+    JpegEncoder::new_with_quality(&mut encoded, quality.clamp(1, 100))
+        .write_image(
+            &flattened,
+            flattened.width(),
+            flattened.height(),
+            ColorType::Rgb8,
+        )
+        .map_err(|e| format!("failed to encode JPEG: {}", e))?;
+    if encoded[..2] != JPEG_SOI {
+        return Err("the JPEG encoder didn't write a JPEG".to_string());
+    }
+    let insert_at = if encoded[2..4] == JPEG_APP0 {
+        4 + u16::from_be_bytes([encoded[4], encoded[5]]) as usize
+    } else {
+        2
+    };
+    let exif = metadata.exif();
+    let mut segment = Vec::with_capacity(exif.len() + 10);
+    segment.extend(JPEG_APP1);
+    // the length includes itself and the identifier
+    segment.extend(((exif.len() + 8) as u16).to_be_bytes());
+    segment.extend(b"Exif\0\0");
+    segment.extend(exif);
+    encoded.splice(insert_at..insert_at, segment);
+    Ok(encoded)
+}
+
+fn riff_chunk(fourcc: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut chunk = Vec::with_capacity(data.len() + 9);
+    chunk.extend(fourcc);
+    chunk.extend((data.len() as u32).to_le_bytes());
+    chunk.extend(data);
+    // chunks are padded to an even size
+    if data.len() % 2 == 1 {
+        chunk.push(0);
+    }
+    chunk
+}
+
+/// Encode `image` as a lossless WebP bitstream and return its chunks, without
+/// the RIFF header.
+fn webp_lossless_chunks(image: &RgbaImage) -> Result<Vec<u8>, String> {
+    let mut simple = Vec::with_capacity(1 << 18);
+    WebPEncoder::new_lossless(&mut simple)
+        .write_image(image, image.width(), image.height(), ColorType::Rgba8)
+        .map_err(|e| format!("failed to encode WebP: {}", e))?;
+    if simple.len() < 12 || &simple[..4] != b"RIFF" || &simple[8..12] != b"WEBP" {
+        return Err("the WebP encoder didn't write a WebP".to_string());
+    }
+    simple.drain(..12);
+    Ok(simple)
+}
+
+/// Write an extended-format WebP file with a VP8X header and then `chunks`.
+fn extended_webp(flags: u8, width: u32, height: u32, chunks: &[u8]) -> Vec<u8> {
+    let mut vp8x = vec![flags, 0, 0, 0];
+    vp8x.extend(&(width - 1).to_le_bytes()[..3]);
+    vp8x.extend(&(height - 1).to_le_bytes()[..3]);
+
+    let mut body = b"WEBP".to_vec();
+    body.extend(riff_chunk(b"VP8X", &vp8x));
+    body.extend(chunks);
+    let mut encoded = b"RIFF".to_vec();
+    encoded.extend((body.len() as u32).to_le_bytes());
+    encoded.extend(body);
+    encoded
+}
+
+/// Encode a lossless WebP. EXIF data needs the extended format, so the
+/// encoder's bitstream is rewrapped with a VP8X header and an EXIF chunk.
+fn encode_webp(image: &RgbaImage, metadata: &ImageMetadata) -> Result<Vec<u8>, String> {
+    let mut chunks = webp_lossless_chunks(image)?;
+    chunks.extend(riff_chunk(b"EXIF", &metadata.exif()));
+    Ok(extended_webp(
+        WEBP_FLAG_EXIF | WEBP_FLAG_ALPHA,
+        image.width(),
+        image.height(),
+        &chunks,
+    ))
+}
+
+/// Encode a looping animated WebP from lossless frames.
+fn encode_animated_webp(
+    frames: &[RgbaImage],
+    delay_ms: u32,
+    metadata: &ImageMetadata,
+) -> Result<Vec<u8>, String> {
+    let Some(first) = frames.first() else {
+        return Err("there are no frames to animate".to_string());
+    };
+    let mut chunks = Vec::new();
+    // white background, loop forever
+    let mut anim = vec![0xff, 0xff, 0xff, 0xff];
+    anim.extend(0u16.to_le_bytes());
+    chunks.extend(riff_chunk(b"ANIM", &anim));
+    for frame in frames {
+        // the frame goes in the top-left corner
+        let mut anmf = vec![0; 6];
+        anmf.extend(&(frame.width() - 1).to_le_bytes()[..3]);
+        anmf.extend(&(frame.height() - 1).to_le_bytes()[..3]);
+        anmf.extend(&delay_ms.min(WEBP_MAX_FRAME_DURATION).to_le_bytes()[..3]);
+        anmf.push(WEBP_FRAME_NO_BLEND);
+        anmf.extend(webp_lossless_chunks(frame)?);
+        chunks.extend(riff_chunk(b"ANMF", &anmf));
+    }
+    chunks.extend(riff_chunk(b"EXIF", &metadata.exif()));
+    Ok(extended_webp(
+        WEBP_FLAG_ANIMATION | WEBP_FLAG_EXIF | WEBP_FLAG_ALPHA,
+        first.width(),
+        first.height(),
+        &chunks,
+    ))
+}
+
+fn encode_gif(frames: &[RgbaImage], delay_ms: u32) -> Result<Vec<u8>, String> {
+    let mut encoded = Vec::with_capacity(1 << 18);
+    let mut encoder = GifEncoder::new_with_speed(&mut encoded, GIF_QUANTIZATION_SPEED);
+    encoder
+        .set_repeat(Repeat::Infinite)
+        .and_then(|_| {
+            encoder.encode_frames(frames.iter().map(|frame| {
+                Frame::from_parts(frame.clone(), 0, 0, Delay::from_numer_denom_ms(delay_ms, 1))
+            }))
+        })
+        .map_err(|e| format!("failed to encode GIF: {}", e))?;
+    drop(encoder);
+    Ok(encoded)
+}
+
+/// Make a one-page PDF the physical size of `image` at `dpi`, with the image
+/// embedded as a JPEG.
+fn encode_pdf(
+    image: &RgbaImage,
+    quality: u8,
+    dpi: f32,
+    metadata: &ImageMetadata,
+) -> Result<Vec<u8>, String> {
+    let jpeg = encode_jpeg(image, quality, metadata)?;
+    let page_width = image.width() as f32 / dpi * PDF_POINTS_PER_INCH;
+    let page_height = image.height() as f32 / dpi * PDF_POINTS_PER_INCH;
+
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let page_id = Ref::new(3);
+    let image_id = Ref::new(4);
+    let content_id = Ref::new(5);
+    let info_id = Ref::new(6);
+    let image_name = Name(b"Im1");
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id).kids([page_id]).count(1);
+    let mut page = pdf.page(page_id);
+    page.media_box(Rect::new(0.0, 0.0, page_width, page_height));
+    page.parent(page_tree_id);
+    page.contents(content_id);
+    page.resources().x_objects().pair(image_name, image_id);
+    page.finish();
+
+    let mut xobject = pdf.image_xobject(image_id, &jpeg);
+    xobject.filter(Filter::DctDecode);
+    xobject.width(image.width() as i32);
+    xobject.height(image.height() as i32);
+    xobject.color_space().device_rgb();
+    xobject.bits_per_component(8);
+    xobject.finish();
+
+    // images are drawn into a 1x1 unit square, so scale it up to the page
+    let mut content = Content::new();
+    content.save_state();
+    content.transform([page_width, 0.0, 0.0, page_height, 0.0, 0.0]);
+    content.x_object(image_name);
+    content.restore_state();
+    pdf.stream(content_id, &content.finish());
+
+    let software = metadata.software();
+    let mut info = pdf.document_info(info_id);
+    info.title(TextStr(&metadata.event_name));
+    info.creator(TextStr(&software));
+    info.creation_date(metadata.pdf_date());
+    if let Some(artist) = &metadata.artist {
+        info.author(TextStr(artist));
+    }
+    info.finish();
+
+    Ok(pdf.finish())
+}
+
+/// Encode `image` in `format` with `metadata` embedded. `dpi` is the
+/// resolution of the image, which sets the page size of PDFs.
+pub fn encode_image(
+    image: &RgbaImage,
+    format: OutputFormat,
+    dpi: f32,
+    metadata: &ImageMetadata,
+) -> Result<Vec<u8>, String> {
+    match format {
+        OutputFormat::Png => {
+            encode_png(image, metadata).map_err(|e| format!("failed to encode PNG: {}", e))
+        }
+        OutputFormat::Jpeg { quality } => encode_jpeg(image, quality, metadata),
+        OutputFormat::WebP => encode_webp(image, metadata),
+        OutputFormat::Pdf { quality } => encode_pdf(image, quality, dpi, metadata),
+    }
+}
+
+/// Encode `frames`, which all have to be the same size, as a looping
+/// animation showing each one for `delay_ms` milliseconds. GIFs have no room
+/// for `metadata`.
+pub fn encode_animation(
+    frames: &[RgbaImage],
+    format: AnimatedLoopFormat,
+    delay_ms: u32,
+    metadata: &ImageMetadata,
+) -> Result<Vec<u8>, String> {
+    match format {
+        AnimatedLoopFormat::Gif => encode_gif(frames, delay_ms),
+        AnimatedLoopFormat::WebP => encode_animated_webp(frames, delay_ms, metadata),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, TimeZone};
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+
+    fn metadata() -> ImageMetadata {
+        ImageMetadata {
+            captured_at: Local.with_ymd_and_hms(2024, 6, 1, 14, 30, 5).unwrap(),
+            event_name: "Example Wedding".to_string(),
+            artist: Some("Example Photo Booth".to_string()),
+            copyright: None,
+        }
+    }
+
+    fn gradient(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([(x * 8) as u8, (y * 8) as u8, 128, 255])
+        })
+    }
+
+    /// A chunk's fourcc and data.
+    type Chunk<'a> = ([u8; 4], &'a [u8]);
+
+    /// The fourcc and data of each chunk in a RIFF body, checking that every
+    /// chunk is padded to an even size and that they fill the body exactly.
+    fn riff_chunks(mut body: &[u8]) -> Vec<Chunk<'_>> {
+        let mut chunks = vec![];
+        while !body.is_empty() {
+            assert!(body.len() >= 8, "a chunk header is cut off");
+            let fourcc = body[..4].try_into().unwrap();
+            let size = u32::from_le_bytes(body[4..8].try_into().unwrap()) as usize;
+            let padded = size + size % 2;
+            assert!(body.len() >= 8 + padded, "a chunk is cut off");
+            if size % 2 == 1 {
+                assert_eq!(body[8 + size], 0, "padding isn't zero");
+            }
+            chunks.push((fourcc, &body[8..8 + size]));
+            body = &body[8 + padded..];
+        }
+        chunks
+    }
+
+    /// The VP8X flags and canvas size, and the chunks after the VP8X one.
+    fn parse_extended_webp(encoded: &[u8]) -> (u8, u32, u32, Vec<Chunk<'_>>) {
+        assert_eq!(&encoded[..4], b"RIFF");
+        let size = u32::from_le_bytes(encoded[4..8].try_into().unwrap()) as usize;
+        assert_eq!(size, encoded.len() - 8);
+        assert_eq!(&encoded[8..12], b"WEBP");
+        let mut chunks = riff_chunks(&encoded[12..]);
+        let (fourcc, vp8x) = chunks.remove(0);
+        assert_eq!(&fourcc, b"VP8X");
+        assert_eq!(vp8x.len(), 10);
+        let u24 = |bytes: &[u8]| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]);
+        (vp8x[0], u24(&vp8x[4..7]) + 1, u24(&vp8x[7..10]) + 1, chunks)
+    }
+
+    /// The text of the ASCII `tag` in an EXIF block, looked for in the first
+    /// IFD and the EXIF IFD it points to.
+    fn exif_text(tiff: &[u8], tag: u16) -> Option<String> {
+        const TAG_EXIF_IFD: u16 = 0x8769;
+        assert_eq!(&tiff[..4], b"II*\0");
+        let u16_at = |offset: usize| u16::from_le_bytes([tiff[offset], tiff[offset + 1]]);
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(tiff[offset..offset + 4].try_into().unwrap());
+        let mut ifds = vec![u32_at(4) as usize];
+        while let Some(ifd) = ifds.pop() {
+            for i in 0..u16_at(ifd) as usize {
+                let entry = ifd + 2 + i * 12;
+                let count = u32_at(entry + 4) as usize;
+                if u16_at(entry) == TAG_EXIF_IFD {
+                    ifds.push(u32_at(entry + 8) as usize);
+                } else if u16_at(entry) == tag {
+                    assert_eq!(u16_at(entry + 2), 2, "the tag isn't ASCII");
+                    let start = if count <= 4 {
+                        entry + 8
+                    } else {
+                        u32_at(entry + 8) as usize
+                    };
+                    let bytes = &tiff[start..start + count];
+                    assert_eq!(bytes.last(), Some(&0), "the text isn't NUL-terminated");
+                    return Some(String::from_utf8(bytes[..count - 1].to_vec()).unwrap());
+                }
+            }
+        }
+        None
+    }
+
+    /// Check that `tiff` has the capture time and the artist from
+    /// [`metadata`].
+    fn assert_exif_matches(tiff: &[u8]) {
+        const TAG_ARTIST: u16 = 0x013b;
+        const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+        assert_eq!(
+            exif_text(tiff, TAG_DATE_TIME_ORIGINAL).as_deref(),
+            Some("2024:06:01 14:30:05")
+        );
+        assert_eq!(
+            exif_text(tiff, TAG_ARTIST).as_deref(),
+            Some("Example Photo Booth")
+        );
+    }
+
+    #[test]
+    fn jpeg_has_exif_after_jfif() {
+        let encoded = encode_jpeg(&gradient(31, 17), 90, &metadata()).unwrap();
+        assert_eq!(encoded[..2], JPEG_SOI);
+        assert_eq!(encoded[2..4], JPEG_APP0);
+        let app1 = 4 + u16::from_be_bytes([encoded[4], encoded[5]]) as usize;
+        assert_eq!(encoded[app1..app1 + 2], JPEG_APP1);
+        let length = u16::from_be_bytes([encoded[app1 + 2], encoded[app1 + 3]]) as usize;
+        assert_eq!(&encoded[app1 + 4..app1 + 10], b"Exif\0\0");
+        assert_exif_matches(&encoded[app1 + 10..app1 + 2 + length]);
+
+        let decoded = image::load_from_memory(&encoded).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (31, 17));
+    }
+
+    #[test]
+    fn png_has_exif_and_text() {
+        let encoded = encode_png(&gradient(31, 17), &metadata()).unwrap();
+        // walk the chunks after the signature to find the EXIF one
+        let mut exif = None;
+        let mut offset = 8;
+        while offset < encoded.len() {
+            let length =
+                u32::from_be_bytes(encoded[offset..offset + 4].try_into().unwrap()) as usize;
+            if &encoded[offset + 4..offset + 8] == b"eXIf" {
+                exif = Some(&encoded[offset + 8..offset + 8 + length]);
+            }
+            // length, type, data and CRC
+            offset += 12 + length;
+        }
+        assert_exif_matches(exif.expect("there's no eXIf chunk"));
+
+        let mut reader = png::Decoder::new(encoded.as_slice()).read_info().unwrap();
+        let author = reader
+            .info()
+            .utf8_text
+            .iter()
+            .find(|chunk| chunk.keyword == "Author")
+            .expect("there's no Author text")
+            .get_text()
+            .unwrap();
+        assert_eq!(author, "Example Photo Booth");
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((frame.width, frame.height), (31, 17));
+    }
+
+    #[test]
+    fn webp_exif_has_the_capture_time() {
+        let encoded = encode_webp(&gradient(31, 17), &metadata()).unwrap();
+        let (_, _, _, chunks) = parse_extended_webp(&encoded);
+        let (_, exif) = chunks
+            .iter()
+            .find(|(fourcc, _)| fourcc == b"EXIF")
+            .expect("there's no EXIF chunk");
+        assert_exif_matches(exif);
+    }
+
+    #[test]
+    fn riff_chunks_are_padded_to_an_even_size() {
+        let chunk = riff_chunk(b"TEST", &[1, 2, 3]);
+        assert_eq!(chunk, [b'T', b'E', b'S', b'T', 3, 0, 0, 0, 1, 2, 3, 0]);
+        let chunk = riff_chunk(b"TEST", &[1, 2]);
+        assert_eq!(chunk, [b'T', b'E', b'S', b'T', 2, 0, 0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn webp_has_a_vp8x_header_and_exif() {
+        let image = gradient(31, 17);
+        let encoded = encode_webp(&image, &metadata()).unwrap();
+        let (flags, width, height, chunks) = parse_extended_webp(&encoded);
+        assert_eq!(flags, WEBP_FLAG_EXIF | WEBP_FLAG_ALPHA);
+        assert_eq!((width, height), (31, 17));
+        let fourccs: Vec<_> = chunks.iter().map(|(fourcc, _)| fourcc).collect();
+        assert_eq!(fourccs, [b"VP8L", b"EXIF"]);
+        assert_eq!(chunks[1].1, metadata().exif());
+    }
+
+    #[test]
+    fn webp_decodes_to_the_same_size() {
+        let image = gradient(31, 17);
+        let encoded = encode_webp(&image, &metadata()).unwrap();
+        let decoded = image::load_from_memory(&encoded).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (31, 17));
+    }
+
+    #[test]
+    fn animated_webp_has_a_frame_chunk_per_frame() {
+        let frames = [gradient(9, 7), gradient(9, 7), gradient(9, 7)];
+        let encoded = encode_animated_webp(&frames, 250, &metadata()).unwrap();
+        let (flags, width, height, chunks) = parse_extended_webp(&encoded);
+        assert_eq!(
+            flags,
+            WEBP_FLAG_ANIMATION | WEBP_FLAG_EXIF | WEBP_FLAG_ALPHA
+        );
+        assert_eq!((width, height), (9, 7));
+        let fourccs: Vec<_> = chunks.iter().map(|(fourcc, _)| fourcc).collect();
+        assert_eq!(fourccs, [b"ANIM", b"ANMF", b"ANMF", b"ANMF", b"EXIF"]);
+        for (_, anmf) in &chunks[1..4] {
+            // offset, size minus one, duration and flags, then the bitstream
+            assert_eq!(&anmf[..6], [0; 6]);
+            assert_eq!(&anmf[6..12], [8, 0, 0, 6, 0, 0]);
+            assert_eq!(&anmf[12..15], [250, 0, 0]);
+            assert_eq!(anmf[15], WEBP_FRAME_NO_BLEND);
+            let frame_chunks = riff_chunks(&anmf[16..]);
+            assert_eq!(&frame_chunks[0].0, b"VP8L");
+        }
+    }
+}
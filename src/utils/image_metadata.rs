@@ -0,0 +1,217 @@
+use chrono::{DateTime, Datelike, Local, Timelike};
+
+use crate::config::Config;
+
+const EXIF_TAG_IMAGE_DESCRIPTION: u16 = 0x010e;
+const EXIF_TAG_SOFTWARE: u16 = 0x0131;
+const EXIF_TAG_DATE_TIME: u16 = 0x0132;
+const EXIF_TAG_ARTIST: u16 = 0x013b;
+const EXIF_TAG_COPYRIGHT: u16 = 0x8298;
+const EXIF_TAG_EXIF_IFD: u16 = 0x8769;
+const EXIF_TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const EXIF_TAG_DATE_TIME_DIGITIZED: u16 = 0x9004;
+const EXIF_TAG_OFFSET_TIME_ORIGINAL: u16 = 0x9011;
+
+const EXIF_TYPE_ASCII: u16 = 2;
+const EXIF_TYPE_LONG: u16 = 4;
+
+/// Size of the TIFF header at the start of an EXIF block.
+const TIFF_HEADER_SIZE: usize = 8;
+
+enum ExifValue {
+    Ascii(String),
+    Long(u32),
+}
+
+/// `text` as plain ASCII, which is all EXIF text tags can hold. Common
+/// accented letters and symbols are spelled out in ASCII and anything else is
+/// left out, along with NULs, which would end the text early.
+fn exif_ascii(text: &str) -> String {
+    let mut ascii = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii() {
+            if c != '\0' {
+                ascii.push(c);
+            }
+            continue;
+        }
+        ascii.push_str(match c {
+            '©' => "(c)",
+            '®' => "(R)",
+            '™' => "(TM)",
+            '‘' | '’' | '‚' => "'",
+            '“' | '”' | '„' => "\"",
+            '–' | '—' => "-",
+            '…' => "...",
+            '•' | '·' => "*",
+            '×' => "x",
+            '€' => "EUR",
+            '£' => "GBP",
+            'À'..='Å' => "A",
+            'à'..='å' => "a",
+            'Æ' => "AE",
+            'æ' => "ae",
+            'Ç' => "C",
+            'ç' => "c",
+            'È'..='Ë' => "E",
+            'è'..='ë' => "e",
+            'Ì'..='Ï' => "I",
+            'ì'..='ï' => "i",
+            'Ñ' => "N",
+            'ñ' => "n",
+            'Ò'..='Ö' | 'Ø' => "O",
+            'ò'..='ö' | 'ø' => "o",
+            'Œ' => "OE",
+            'œ' => "oe",
+            'Ù'..='Ü' => "U",
+            'ù'..='ü' => "u",
+            'Ý' | 'Ÿ' => "Y",
+            'ý' | 'ÿ' => "y",
+            'ß' => "ss",
+            _ => "",
+        });
+    }
+    ascii
+}
+
+fn ifd_size(entries: usize) -> usize {
+    2 + entries * 12 + 4
+}
+
+/// Write an IFD with `entries` sorted by tag. Values that don't fit in an
+/// entry go into `data`, which starts at `data_start` in the TIFF block.
+fn write_ifd(
+    tiff: &mut Vec<u8>,
+    data: &mut Vec<u8>,
+    data_start: usize,
+    entries: &[(u16, ExifValue)],
+) {
+    tiff.extend((entries.len() as u16).to_le_bytes());
+    for (tag, value) in entries {
+        tiff.extend(tag.to_le_bytes());
+        match value {
+            ExifValue::Long(value) => {
+                tiff.extend(EXIF_TYPE_LONG.to_le_bytes());
+                tiff.extend(1u32.to_le_bytes());
+                tiff.extend(value.to_le_bytes());
+            }
+            ExifValue::Ascii(value) => {
+                let mut bytes = exif_ascii(value).into_bytes();
+                bytes.push(0);
+                tiff.extend(EXIF_TYPE_ASCII.to_le_bytes());
+                tiff.extend((bytes.len() as u32).to_le_bytes());
+                if bytes.len() <= 4 {
+                    bytes.resize(4, 0);
+                    tiff.extend(bytes);
+                } else {
+                    tiff.extend(((data_start + data.len()) as u32).to_le_bytes());
+                    data.extend(bytes);
+                    // offsets have to be word-aligned
+                    if data.len() % 2 == 1 {
+                        data.push(0);
+                    }
+                }
+            }
+        }
+    }
+    // there's no next IFD
+    tiff.extend(0u32.to_le_bytes());
+}
+
+/// What's written into the metadata of encoded images, so photo apps can sort
+/// and label them.
+#[derive(Debug, Clone)]
+pub struct ImageMetadata {
+    pub captured_at: DateTime<Local>,
+    pub event_name: String,
+    pub artist: Option<String>,
+    pub copyright: Option<String>,
+}
+
+impl ImageMetadata {
+    pub fn new(config: &Config, captured_at: DateTime<Local>) -> Self {
+        ImageMetadata {
+            captured_at,
+            event_name: config.name.clone(),
+            artist: config.artist.clone(),
+            copyright: config.copyright.clone(),
+        }
+    }
+
+    pub fn software(&self) -> String {
+        format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+    }
+
+    /// The metadata as a little-endian TIFF block, as used in JPEG APP1
+    /// segments, PNG eXIf chunks and WebP EXIF chunks.
+    pub fn exif(&self) -> Vec<u8> {
+        let date_time = self.captured_at.format("%Y:%m:%d %H:%M:%S").to_string();
+        let mut ifd0 = vec![
+            (
+                EXIF_TAG_IMAGE_DESCRIPTION,
+                ExifValue::Ascii(self.event_name.clone()),
+            ),
+            (EXIF_TAG_SOFTWARE, ExifValue::Ascii(self.software())),
+            (EXIF_TAG_DATE_TIME, ExifValue::Ascii(date_time.clone())),
+        ];
+        if let Some(artist) = &self.artist {
+            ifd0.push((EXIF_TAG_ARTIST, ExifValue::Ascii(artist.clone())));
+        }
+        if let Some(copyright) = &self.copyright {
+            ifd0.push((EXIF_TAG_COPYRIGHT, ExifValue::Ascii(copyright.clone())));
+        }
+        let exif_ifd = [
+            (
+                EXIF_TAG_DATE_TIME_ORIGINAL,
+                ExifValue::Ascii(date_time.clone()),
+            ),
+            (EXIF_TAG_DATE_TIME_DIGITIZED, ExifValue::Ascii(date_time)),
+            (
+                EXIF_TAG_OFFSET_TIME_ORIGINAL,
+                ExifValue::Ascii(self.captured_at.format("%:z").to_string()),
+            ),
+        ];
+        let exif_ifd_offset = TIFF_HEADER_SIZE + ifd_size(ifd0.len() + 1);
+        ifd0.push((EXIF_TAG_EXIF_IFD, ExifValue::Long(exif_ifd_offset as u32)));
+        let data_start = exif_ifd_offset + ifd_size(exif_ifd.len());
+
+        let mut tiff = Vec::with_capacity(data_start);
+        tiff.extend(b"II*\0");
+        tiff.extend((TIFF_HEADER_SIZE as u32).to_le_bytes());
+        let mut data = Vec::new();
+        write_ifd(&mut tiff, &mut data, data_start, &ifd0);
+        write_ifd(&mut tiff, &mut data, data_start, &exif_ifd);
+        tiff.extend(data);
+        tiff
+    }
+
+    /// Keyword and text pairs for PNG text chunks, using the keywords from
+    /// the PNG specification.
+    pub fn png_text(&self) -> Vec<(String, String)> {
+        let mut text = vec![
+            ("Title".to_string(), self.event_name.clone()),
+            ("Software".to_string(), self.software()),
+            ("Creation Time".to_string(), self.captured_at.to_rfc2822()),
+        ];
+        if let Some(artist) = &self.artist {
+            text.push(("Author".to_string(), artist.clone()));
+        }
+        if let Some(copyright) = &self.copyright {
+            text.push(("Copyright".to_string(), copyright.clone()));
+        }
+        text
+    }
+
+    /// The capture time as a PDF date.
+    pub fn pdf_date(&self) -> pdf_writer::Date {
+        let offset_minutes = self.captured_at.offset().local_minus_utc() / 60;
+        pdf_writer::Date::new(self.captured_at.year() as u16)
+            .month(self.captured_at.month() as u8)
+            .day(self.captured_at.day() as u8)
+            .hour(self.captured_at.hour() as u8)
+            .minute(self.captured_at.minute() as u8)
+            .second(self.captured_at.second() as u8)
+            .utc_offset_hour((offset_minutes / 60) as i8)
+            .utc_offset_minute((offset_minutes % 60).unsigned_abs() as u8)
+    }
+}
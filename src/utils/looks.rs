@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use crate::config::{ColorFilter, Look};
+
+use super::color_lut::Lut3d;
+
+/// Points along each axis of the tables made for the built-in filters. They're
+/// smooth, so this is plenty.
+const BUILT_IN_LUT_SIZE: usize = 17;
+
+/// A look with its filter ready to apply.
+#[derive(Debug, Clone)]
+pub struct LoadedLook {
+    pub label: String,
+    pub lut: Option<Arc<Lut3d>>,
+}
+
+fn luma([r, g, b]: [f32; 3]) -> f32 {
+    0.299 * r + 0.587 * g + 0.114 * b
+}
+
+fn filter_lut(filter: &ColorFilter) -> Result<Lut3d, String> {
+    Ok(match filter {
+        ColorFilter::BlackAndWhite => Lut3d::from_fn(BUILT_IN_LUT_SIZE, |color| {
+            let luma = luma(color);
+            [luma, luma, luma]
+        }),
+        ColorFilter::Sepia => Lut3d::from_fn(BUILT_IN_LUT_SIZE, |[r, g, b]| {
+            [
+                0.393 * r + 0.769 * g + 0.189 * b,
+                0.349 * r + 0.686 * g + 0.168 * b,
+                0.272 * r + 0.534 * g + 0.131 * b,
+            ]
+        }),
+        // an S-curve, so nothing gets clipped
+        ColorFilter::HighContrast => Lut3d::from_fn(BUILT_IN_LUT_SIZE, |color| {
+            color.map(|value| value * value * (3.0 - 2.0 * value))
+        }),
+        ColorFilter::Warm => Lut3d::from_fn(BUILT_IN_LUT_SIZE, |[r, g, b]| {
+            [r * 1.08, g * 1.02, b * 0.88]
+        }),
+        ColorFilter::Cool => {
+            Lut3d::from_fn(BUILT_IN_LUT_SIZE, |[r, g, b]| [r * 0.88, g * 1.0, b * 1.08])
+        }
+        ColorFilter::Lut { path } => {
+            let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+            Lut3d::parse_cube(&source)?
+        }
+    })
+}
+
+/// Get the looks from the config ready to apply. A look whose LUT can't be
+/// loaded is left unfiltered, and a single unfiltered look is used if none
+/// are configured.
+pub fn load_looks(looks: &[Look]) -> Vec<LoadedLook> {
+    if looks.is_empty() {
+        return vec![LoadedLook {
+            label: "Original".to_string(),
+            lut: None,
+        }];
+    }
+    looks
+        .iter()
+        .map(|look| LoadedLook {
+            label: look.label.clone(),
+            lut: look
+                .filter
+                .as_ref()
+                .and_then(|filter| match filter_lut(filter) {
+                    Ok(lut) => Some(Arc::new(lut)),
+                    Err(e) => {
+                        eprintln!(
+                            "warning: failed to load the filter for the look {:?}: {}",
+                            look.label, e
+                        );
+                        None
+                    }
+                }),
+        })
+        .collect()
+}
@@ -0,0 +1,264 @@
+use std::{
+    borrow::Cow,
+    sync::{Arc, OnceLock},
+};
+
+use image::{
+    imageops::{self, FilterType},
+    RgbaImage,
+};
+
+use super::{
+    border_radius::{BorderRadius, CornerMask},
+    chroma_key::{cover, ChromaKeyer},
+    color_lut::Lut3d,
+    crop::{crop_rect, zoom_rect},
+};
+
+/// Where to sample the source for one output row or column: the two
+/// neighbouring source indices and the weight of the second one, out of 256.
+#[derive(Clone, Copy)]
+struct Tap {
+    near: usize,
+    far: usize,
+    weight: u32,
+}
+
+fn taps(output_len: u32, source_start: u32, source_len: u32, reversed: bool) -> Vec<Tap> {
+    let scale = source_len as f32 / output_len as f32;
+    (0..output_len)
+        .map(|i| {
+            let i = if reversed { output_len - 1 - i } else { i };
+            let position = ((i as f32 + 0.5) * scale - 0.5).clamp(0.0, (source_len - 1) as f32);
+            let near = position as u32;
+            let far = (near + 1).min(source_len - 1);
+            Tap {
+                near: (source_start + near) as usize,
+                far: (source_start + far) as usize,
+                weight: ((position - near as f32) * 256.0) as u32,
+            }
+        })
+        .collect()
+}
+
+/// Blend two RGBA pixels packed into `u32`s, all four channels at once.
+/// `weight` is how much of `b` to use, out of 256.
+fn lerp(a: u32, b: u32, weight: u32) -> u32 {
+    let inverse = 256 - weight;
+    let even = (((a & 0x00ff00ff) * inverse + (b & 0x00ff00ff) * weight) >> 8) & 0x00ff00ff;
+    let odd = (((a >> 8) & 0x00ff00ff) * inverse + ((b >> 8) & 0x00ff00ff) * weight) & 0xff00ff00;
+    even | odd
+}
+
+/// Turns camera frames into what's shown in the live preview.
+///
+/// Frames are zoomed, cropped, mirrored and scaled down to fit the display in a
+/// single pass, so the expensive work happens on as few pixels as possible.
+/// The chroma key and the colour filter for the chosen look are applied
+/// after scaling, and the rounded corners come from a [`CornerMask`] that's
+/// only worked out once.
+#[derive(Debug, Clone)]
+pub struct PreviewRenderer {
+    corner_mask: CornerMask,
+    mirror: bool,
+    aspect_ratio: Option<f32>,
+    zoom: f32,
+    zoom_centre: (f32, f32),
+    max_size: Option<(u32, u32)>,
+    lut: Option<Arc<Lut3d>>,
+    chroma_key: Option<(ChromaKeyer, Arc<RgbaImage>)>,
+    /// The chroma key background scaled to cover the zoomed-in frame at the
+    /// output's scale, made on the first frame after anything that changes it.
+    scaled_background: OnceLock<RgbaImage>,
+}
+
+impl PreviewRenderer {
+    pub fn new(border_radius: BorderRadius, mirror: bool, aspect_ratio: Option<f32>) -> Self {
+        PreviewRenderer {
+            corner_mask: CornerMask::new(border_radius),
+            mirror,
+            aspect_ratio,
+            zoom: 1.0,
+            zoom_centre: (0.5, 0.5),
+            max_size: None,
+            lut: None,
+            chroma_key: None,
+            scaled_background: OnceLock::new(),
+        }
+    }
+
+    /// Replace the backdrop with `background` using `keyer`, or stop
+    /// replacing it.
+    pub fn set_chroma_key(&mut self, chroma_key: Option<(ChromaKeyer, Arc<RgbaImage>)>) {
+        self.chroma_key = chroma_key;
+        self.scaled_background = OnceLock::new();
+    }
+
+    /// Crop frames to `aspect_ratio`, or stop cropping them.
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: Option<f32>) {
+        self.aspect_ratio = aspect_ratio;
+        self.scaled_background = OnceLock::new();
+    }
+
+    /// Filter the output through `lut`, or stop filtering it.
+    pub fn set_lut(&mut self, lut: Option<Arc<Lut3d>>) {
+        self.lut = lut;
+    }
+
+    /// Limit the output to fit inside `width` by `height`. Frames are never
+    /// scaled up.
+    pub fn set_max_size(&mut self, width: u32, height: u32) {
+        self.max_size = Some((width.max(1), height.max(1)));
+        self.scaled_background = OnceLock::new();
+    }
+
+    /// Zoom in by `zoom` around `centre`, given as fractions of the frame's
+    /// width and height.
+    pub fn set_zoom(&mut self, zoom: f32, centre: (f32, f32)) {
+        self.zoom = zoom;
+        self.zoom_centre = centre;
+        self.scaled_background = OnceLock::new();
+    }
+
+    /// The zoomed-in part of a `width` by `height` frame, as `(left, top,
+    /// width, height)`.
+    pub fn zoom_rect(&self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        zoom_rect(width, height, self.zoom, self.zoom_centre)
+    }
+
+    /// The part of a `width` by `height` frame that's shown, with the crop
+    /// centred on `focus` inside the zoomed-in part.
+    fn crop(&self, width: u32, height: u32, focus: (f32, f32)) -> (u32, u32, u32, u32) {
+        let (zoom_left, zoom_top, zoom_width, zoom_height) = self.zoom_rect(width, height);
+        let (left, top, crop_width, crop_height) =
+            crop_rect(zoom_width, zoom_height, self.aspect_ratio, focus);
+        (zoom_left + left, zoom_top + top, crop_width, crop_height)
+    }
+
+    /// The size of the output for a `width` by `height` frame.
+    pub fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        // the size of the crop doesn't depend on where it's centred
+        let (_, _, crop_width, crop_height) = self.crop(width, height, (0.5, 0.5));
+        match self.max_size {
+            Some((max_width, max_height)) if crop_width > max_width || crop_height > max_height => {
+                let scale = (max_width as f32 / crop_width as f32)
+                    .min(max_height as f32 / crop_height as f32);
+                (
+                    ((crop_width as f32 * scale) as u32).max(1),
+                    ((crop_height as f32 * scale) as u32).max(1),
+                )
+            }
+            _ => (crop_width, crop_height),
+        }
+    }
+
+    /// Render `frame` with the crop centred on `focus`, as fractions of the
+    /// width and height of its zoomed-in part, like in captured photos.
+    pub fn render(&self, frame: &RgbaImage, focus: (f32, f32)) -> RgbaImage {
+        let (crop_left, crop_top, crop_width, crop_height) =
+            self.crop(frame.width(), frame.height(), focus);
+        let (width, height) = self.output_size(frame.width(), frame.height());
+        let columns = taps(width, crop_left, crop_width, self.mirror);
+        let rows = taps(height, crop_top, crop_height, false);
+
+        let source = frame.as_raw();
+        let stride = frame.width() as usize * 4;
+        let row_len = width as usize * 4;
+        let mut output = vec![0; row_len * height as usize];
+        if (width, height) == (crop_width, crop_height) {
+            // nothing to scale, so just copy the cropped rows over
+            for (output_row, row) in output.chunks_exact_mut(row_len).zip(&rows) {
+                let source_row = &source[row.near * stride + crop_left as usize * 4..][..row_len];
+                if self.mirror {
+                    for (pixel, source_pixel) in output_row
+                        .chunks_exact_mut(4)
+                        .zip(source_row.chunks_exact(4).rev())
+                    {
+                        pixel.copy_from_slice(source_pixel);
+                    }
+                } else {
+                    output_row.copy_from_slice(source_row);
+                }
+            }
+        } else {
+            let pixel = |line: &[u8], index: usize| {
+                u32::from_ne_bytes(
+                    line[index * 4..index * 4 + 4]
+                        .try_into()
+                        .expect("a pixel is four bytes"),
+                )
+            };
+            for (output_row, row) in output.chunks_exact_mut(row_len).zip(&rows) {
+                let near_row = &source[row.near * stride..][..stride];
+                let far_row = &source[row.far * stride..][..stride];
+                for (output_pixel, column) in output_row.chunks_exact_mut(4).zip(&columns) {
+                    let near = lerp(
+                        pixel(near_row, column.near),
+                        pixel(near_row, column.far),
+                        column.weight,
+                    );
+                    let far = lerp(
+                        pixel(far_row, column.near),
+                        pixel(far_row, column.far),
+                        column.weight,
+                    );
+                    output_pixel.copy_from_slice(&lerp(near, far, row.weight).to_ne_bytes());
+                }
+            }
+        }
+
+        let mut output = RgbaImage::from_raw(width, height, output)
+            .expect("preview buffer is the size of the preview");
+        if let Some((keyer, background)) = &self.chroma_key {
+            // fit the background to the whole zoomed-in frame and show the
+            // part of it behind the crop, the same as in the captured photos
+            let (zoom_left, zoom_top, zoom_width, zoom_height) =
+                self.zoom_rect(frame.width(), frame.height());
+            let scale_x = width as f32 / crop_width as f32;
+            let scale_y = height as f32 / crop_height as f32;
+            let scaled_width = ((zoom_width as f32 * scale_x).round() as u32).max(width);
+            let scaled_height = ((zoom_height as f32 * scale_y).round() as u32).max(height);
+            // the preview is small, so a cheaper filter than the strip's is
+            // fine for the background
+            let scale = || {
+                cover(
+                    background,
+                    scaled_width,
+                    scaled_height,
+                    FilterType::Triangle,
+                )
+            };
+            let scaled = self.scaled_background.get_or_init(scale);
+            let scaled = if scaled.dimensions() == (scaled_width, scaled_height) {
+                Cow::Borrowed(scaled)
+            } else {
+                // only if the camera changes resolution
+                Cow::Owned(scale())
+            };
+            // the background isn't mirrored, so the crop is found where it's
+            // shown rather than where it is in the camera's frame
+            let left = crop_left - zoom_left;
+            let left = if self.mirror {
+                zoom_width - crop_width - left
+            } else {
+                left
+            };
+            let left = ((left as f32 * scale_x).round() as u32).min(scaled_width - width);
+            let top = (((crop_top - zoom_top) as f32 * scale_y).round() as u32)
+                .min(scaled_height - height);
+            if scaled.dimensions() == (width, height) {
+                keyer.composite(&mut output, &scaled);
+            } else {
+                keyer.composite(
+                    &mut output,
+                    &imageops::crop_imm(&*scaled, left, top, width, height).to_image(),
+                );
+            }
+        }
+        if let Some(lut) = &self.lut {
+            lut.apply(&mut output);
+        }
+        self.corner_mask.apply(&mut output);
+        output
+    }
+}
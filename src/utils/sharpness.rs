@@ -0,0 +1,71 @@
+use image::RgbaImage;
+
+/// Perceived brightness of a pixel, using the Rec. 601 weights.
+fn luma(pixel: &image::Rgba<u8>) -> f64 {
+    let [r, g, b, _] = pixel.0;
+    0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64
+}
+
+/// Score how sharp `image` is as the variance of the Laplacian of its
+/// brightness. Blurry images have soft edges, so the Laplacian stays close to
+/// zero and the score is low. Scores are only comparable between images of the
+/// same scene and size, like the frames of a burst.
+pub fn laplacian_variance(image: &RgbaImage) -> f64 {
+    let (width, height) = image.dimensions();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+    let luma: Vec<f64> = image.pixels().map(luma).collect();
+    let at = |x: u32, y: u32| luma[(y * width + x) as usize];
+
+    let mut sum = 0.0;
+    let mut sum_of_squares = 0.0;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let laplacian =
+                at(x - 1, y) + at(x + 1, y) + at(x, y - 1) + at(x, y + 1) - 4.0 * at(x, y);
+            sum += laplacian;
+            sum_of_squares += laplacian * laplacian;
+        }
+    }
+    let count = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / count;
+    sum_of_squares / count - mean * mean
+}
+
+#[cfg(test)]
+mod tests {
+    use super::laplacian_variance;
+
+    fn fixture(bytes: &[u8]) -> image::RgbaImage {
+        image::load_from_memory(bytes)
+            .expect("failed to decode fixture")
+            .into_rgba8()
+    }
+
+    #[test]
+    fn sharp_beats_gaussian_blur() {
+        let sharp = fixture(include_bytes!("../../tests/fixtures/sharp.png"));
+        let blurred = fixture(include_bytes!("../../tests/fixtures/blurred.png"));
+        assert!(laplacian_variance(&sharp) > laplacian_variance(&blurred) * 2.0);
+    }
+
+    #[test]
+    fn sharp_beats_motion_blur() {
+        let sharp = fixture(include_bytes!("../../tests/fixtures/sharp.png"));
+        let motion_blurred = fixture(include_bytes!("../../tests/fixtures/motion_blurred.png"));
+        assert!(laplacian_variance(&sharp) > laplacian_variance(&motion_blurred));
+    }
+
+    #[test]
+    fn flat_image_scores_zero() {
+        let flat = image::RgbaImage::from_pixel(32, 32, image::Rgba([120, 80, 200, 255]));
+        assert_eq!(laplacian_variance(&flat), 0.0);
+    }
+
+    #[test]
+    fn tiny_image_scores_zero() {
+        let tiny = image::RgbaImage::from_pixel(2, 2, image::Rgba([255, 255, 255, 255]));
+        assert_eq!(laplacian_variance(&tiny), 0.0);
+    }
+}
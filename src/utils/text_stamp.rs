@@ -0,0 +1,247 @@
+use std::{
+    collections::hash_map::RandomState,
+    fmt::Write,
+    hash::{BuildHasher, Hasher},
+};
+
+use ab_glyph::{point, Font, FontVec, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+
+use crate::config::{Config, Template, TextAlign, TextElement};
+
+/// Characters used for session codes. Look-alikes like 0/O and 1/I are left
+/// out so codes can be read back off a print.
+const SESSION_ID_ALPHABET: &[u8] = b"23456789ABCDEFGHJKLMNPQRSTUVWXYZ";
+const SESSION_ID_LENGTH: usize = 6;
+
+fn session_id() -> String {
+    let mut random = RandomState::new().build_hasher().finish();
+    (0..SESSION_ID_LENGTH)
+        .map(|_| {
+            let c = SESSION_ID_ALPHABET[(random % SESSION_ID_ALPHABET.len() as u64) as usize];
+            random /= SESSION_ID_ALPHABET.len() as u64;
+            c as char
+        })
+        .collect()
+}
+
+fn format_now(now: &chrono::DateTime<chrono::Local>, format: &str) -> String {
+    let mut formatted = String::new();
+    if write!(formatted, "{}", now.format(format)).is_err() {
+        eprintln!("warning: invalid date/time format {:?}", format);
+        formatted.clear();
+    }
+    formatted
+}
+
+/// The values filled into the placeholders of template texts. One of these is
+/// made for every guest session.
+#[derive(Debug, Clone)]
+pub struct StampValues {
+    pub captured_at: chrono::DateTime<chrono::Local>,
+    pub name: String,
+    pub date: String,
+    pub time: String,
+    pub session_id: String,
+}
+
+impl StampValues {
+    /// Make the values for a session starting now.
+    pub fn new(config: &Config) -> Self {
+        let now = chrono::Local::now();
+        StampValues {
+            captured_at: now,
+            name: config.name.clone(),
+            date: format_now(&now, &config.date_format),
+            time: format_now(&now, &config.time_format),
+            session_id: session_id(),
+        }
+    }
+
+    pub fn fill(&self, content: &str) -> String {
+        content
+            .replace("{name}", &self.name)
+            .replace("{date}", &self.date)
+            .replace("{time}", &self.time)
+            .replace("{session_id}", &self.session_id)
+    }
+}
+
+/// Blend `color` with the given coverage over `pixel`.
+fn blend(pixel: &mut Rgba<u8>, color: [u8; 4], coverage: f32) {
+    let source_alpha = color[3] as f32 / 255.0 * coverage;
+    if source_alpha <= 0.0 {
+        return;
+    }
+    let destination_alpha = pixel.0[3] as f32 / 255.0;
+    let alpha = source_alpha + destination_alpha * (1.0 - source_alpha);
+    for (channel, color) in pixel.0.iter_mut().zip(color).take(3) {
+        *channel = ((color as f32 * source_alpha
+            + *channel as f32 * destination_alpha * (1.0 - source_alpha))
+            / alpha)
+            .round() as u8;
+    }
+    pixel.0[3] = (alpha * 255.0).round() as u8;
+}
+
+/// Glyph coverage of some unrotated text, plus the point it's anchored to.
+struct Coverage {
+    width: usize,
+    height: usize,
+    values: Vec<f32>,
+    anchor_x: f32,
+}
+
+impl Coverage {
+    fn get(&self, x: i64, y: i64) -> f32 {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            0.0
+        } else {
+            self.values[y as usize * self.width + x as usize]
+        }
+    }
+
+    /// Bilinearly sample the coverage at a fractional position.
+    fn sample(&self, x: f32, y: f32) -> f32 {
+        let x = x - 0.5;
+        let y = y - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let top = self.get(x0, y0) * (1.0 - fx) + self.get(x0 + 1, y0) * fx;
+        let bottom = self.get(x0, y0 + 1) * (1.0 - fx) + self.get(x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+fn rasterize(font: &FontVec, text: &str, size: f32, align: TextAlign) -> Coverage {
+    let font = font.as_scaled(PxScale::from(size));
+    let line_height = font.height() + font.line_gap();
+
+    // lay out every line first to find out how wide the text is
+    let lines: Vec<(f32, Vec<ab_glyph::Glyph>)> = text
+        .lines()
+        .enumerate()
+        .map(|(line_index, line)| {
+            let mut caret = 0.0;
+            let mut previous = None;
+            let glyphs = line
+                .chars()
+                .map(|c| {
+                    let id = font.glyph_id(c);
+                    if let Some(previous) = previous {
+                        caret += font.kern(previous, id);
+                    }
+                    previous = Some(id);
+                    let glyph = id.with_scale_and_position(
+                        size,
+                        point(caret, font.ascent() + line_index as f32 * line_height),
+                    );
+                    caret += font.h_advance(id);
+                    glyph
+                })
+                .collect();
+            (caret, glyphs)
+        })
+        .collect();
+    let text_width = lines.iter().map(|(width, _)| *width).fold(0.0, f32::max);
+
+    let width = text_width.ceil() as usize + 1;
+    let height = (lines.len() as f32 * line_height).ceil() as usize + 1;
+    let mut values = vec![0.0f32; width * height];
+    for (line_width, glyphs) in lines {
+        let offset = match align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => (text_width - line_width) / 2.0,
+            TextAlign::Right => text_width - line_width,
+        };
+        for mut glyph in glyphs {
+            glyph.position.x += offset;
+            if let Some(outlined) = font.outline_glyph(glyph) {
+                let bounds = outlined.px_bounds();
+                outlined.draw(|x, y, c| {
+                    let x = bounds.min.x as i64 + x as i64;
+                    let y = bounds.min.y as i64 + y as i64;
+                    if x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height {
+                        let value = &mut values[y as usize * width + x as usize];
+                        *value = value.max(c);
+                    }
+                });
+            }
+        }
+    }
+    Coverage {
+        width,
+        height,
+        values,
+        anchor_x: match align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => text_width / 2.0,
+            TextAlign::Right => text_width,
+        },
+    }
+}
+
+fn stamp_text(image: &mut RgbaImage, element: &TextElement, content: &str, scale: f32) {
+    let font = match std::fs::read(&element.font)
+        .map_err(|e| e.to_string())
+        .and_then(|data| FontVec::try_from_vec(data).map_err(|e| e.to_string()))
+    {
+        Ok(font) => font,
+        Err(e) => {
+            eprintln!(
+                "warning: skipping template text, failed to load font {:?}: {}",
+                element.font, e
+            );
+            return;
+        }
+    };
+    let coverage = rasterize(&font, content, element.size * scale, element.align);
+
+    let (sin, cos) = element.rotation.to_radians().sin_cos();
+    let anchor_x = element.x * scale;
+    let anchor_y = element.y * scale;
+
+    // find the area of the image the rotated text covers
+    let corners = [
+        (0.0, 0.0),
+        (coverage.width as f32, 0.0),
+        (0.0, coverage.height as f32),
+        (coverage.width as f32, coverage.height as f32),
+    ]
+    .map(|(x, y)| {
+        let x = x - coverage.anchor_x;
+        (anchor_x + x * cos - y * sin, anchor_y + x * sin + y * cos)
+    });
+    let min_x = corners.iter().map(|c| c.0).fold(f32::MAX, f32::min).floor();
+    let max_x = corners.iter().map(|c| c.0).fold(f32::MIN, f32::max).ceil();
+    let min_y = corners.iter().map(|c| c.1).fold(f32::MAX, f32::min).floor();
+    let max_y = corners.iter().map(|c| c.1).fold(f32::MIN, f32::max).ceil();
+    let x_range = (min_x.max(0.0) as u32)..(max_x.max(0.0) as u32).min(image.width());
+    let y_range = (min_y.max(0.0) as u32)..(max_y.max(0.0) as u32).min(image.height());
+
+    for y in y_range {
+        for x in x_range.clone() {
+            // undo the rotation to find where this pixel is in the text
+            let dx = x as f32 + 0.5 - anchor_x;
+            let dy = y as f32 + 0.5 - anchor_y;
+            let text_x = dx * cos + dy * sin + coverage.anchor_x;
+            let text_y = -dx * sin + dy * cos;
+            blend(
+                image.get_pixel_mut(x, y),
+                element.color,
+                coverage.sample(text_x, text_y),
+            );
+        }
+    }
+}
+
+/// Draw the texts of `template` onto `image`, which can be any size as long as
+/// it has the template's aspect ratio. Texts whose font can't be loaded are
+/// skipped with a warning.
+pub fn stamp_texts(image: &mut RgbaImage, template: &Template, values: &StampValues) {
+    let scale = image.width() as f32 / template.width;
+    for element in &template.texts {
+        stamp_text(image, element, &values.fill(&element.content), scale);
+    }
+}